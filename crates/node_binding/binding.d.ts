@@ -519,11 +519,47 @@ export interface RawCrossOriginLoading {
 export interface RawCssModulesConfig {
   localsConvention: "asIs" | "camelCase" | "camelCaseOnly" | "dashes" | "dashesOnly"
   localIdentName: string
+  hashStrategy?: "resource-path" | "resource-content" | "resource-path-and-content"
+  hashPrefix?: string
+  prefix?: string
+  localIdentMaxLength?: number
+  hashFunction?: string
+  hashDigest?: string
+  hashDigestLength?: number | "full"
+  hashSalt?: string
   exportsOnly: boolean
+  esModule?: boolean
+  exportsType?: "commonjs" | "default" | "namespace"
+  namedExport?: boolean
+  exportGlobals?: boolean
+  exportsByConvention?: boolean
+  composesSeparator?: string
+  exportClassNames?: "string" | "array" | "always-array"
+  lazyComposes?: boolean
+  sortExports?: boolean
+  pathinfo?: boolean
+  singleQuote?: boolean
+  freezeExports?: boolean
+  resilient?: boolean
+  lineEnding?: "lf" | "crlf"
+  trailingNewline?: boolean
+  detectIdentCollisions?: boolean
+  pure?: boolean
+  identManifest?: string
+  exportContentHash?: boolean
+  hashPackageName?: boolean
+  identStart?: "underscore" | "letter"
+  invalidEscape?: "replace" | "keep"
+  localScope?: "class" | "attribute"
+  localIdentRegExp?: string
 }
 
 export interface RawCssPluginConfig {
   modules: RawCssModulesConfig
+  relativeUrls?: boolean
+  lenientUrls?: boolean
+  keepUrlPercentEscapes?: boolean
+  debugCounters?: boolean
 }
 
 export interface RawDecoratorOptions {
@@ -143,6 +143,11 @@ impl RawOptionsApply for RawBuiltins {
           .unwrap_or_default(),
         postcss: self.postcss.unwrap_or_default().into(),
         modules: css.modules.try_into()?,
+        relative_urls: css.relative_urls.unwrap_or_default(),
+        lenient_urls: css.lenient_urls.unwrap_or_default(),
+        keep_url_percent_escapes: css.keep_url_percent_escapes.unwrap_or_default(),
+        debug_counters: css.debug_counters.unwrap_or_default(),
+        record_hashes: false,
       };
       plugins.push(CssPlugin::new(options).boxed());
     }
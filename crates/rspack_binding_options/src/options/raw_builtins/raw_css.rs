@@ -1,7 +1,11 @@
 use std::str::FromStr;
 
 use napi_derive::napi;
-use rspack_plugin_css::plugin::{LocalIdentName, LocalsConvention, ModulesConfig};
+use rspack_error::internal_error;
+use rspack_plugin_css::plugin::{
+  ExportClassNames, ExportsType, IdentStart, InvalidEscape, LineEnding, LocalIdentHashStrategy,
+  LocalIdentName, LocalScope, LocalsConvention, ModulesConfig, FULL_HASH_DIGEST_LENGTH,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug, Serialize, Clone)]
@@ -9,6 +13,10 @@ use serde::{Deserialize, Serialize};
 #[napi(object)]
 pub struct RawCssPluginConfig {
   pub modules: RawCssModulesConfig,
+  pub relative_urls: Option<bool>,
+  pub lenient_urls: Option<bool>,
+  pub keep_url_percent_escapes: Option<bool>,
+  pub debug_counters: Option<bool>,
 }
 
 #[derive(Deserialize, Debug, Serialize, Clone)]
@@ -18,17 +26,183 @@ pub struct RawCssModulesConfig {
   #[napi(ts_type = "\"asIs\" | \"camelCase\" | \"camelCaseOnly\" | \"dashes\" | \"dashesOnly\"")]
   pub locals_convention: String,
   pub local_ident_name: String,
+  #[napi(ts_type = "\"resource-path\" | \"resource-content\" | \"resource-path-and-content\"")]
+  pub hash_strategy: Option<String>,
+  pub hash_prefix: Option<String>,
+  pub prefix: Option<String>,
+  pub local_ident_max_length: Option<u32>,
+  pub hash_function: Option<String>,
+  pub hash_digest: Option<String>,
+  #[napi(ts_type = "number | \"full\"")]
+  pub hash_digest_length: Option<serde_json::Value>,
+  pub hash_salt: Option<String>,
   pub exports_only: bool,
+  pub es_module: Option<bool>,
+  #[napi(ts_type = "\"commonjs\" | \"default\" | \"namespace\"")]
+  pub exports_type: Option<String>,
+  pub named_export: Option<bool>,
+  pub export_globals: Option<bool>,
+  pub exports_by_convention: Option<bool>,
+  pub composes_separator: Option<String>,
+  #[napi(ts_type = "\"string\" | \"array\" | \"always-array\"")]
+  pub export_class_names: Option<String>,
+  pub lazy_composes: Option<bool>,
+  pub sort_exports: Option<bool>,
+  pub pathinfo: Option<bool>,
+  pub single_quote: Option<bool>,
+  pub freeze_exports: Option<bool>,
+  pub resilient: Option<bool>,
+  #[napi(ts_type = "\"lf\" | \"crlf\"")]
+  pub line_ending: Option<String>,
+  pub trailing_newline: Option<bool>,
+  pub detect_ident_collisions: Option<bool>,
+  pub pure: Option<bool>,
+  pub ident_manifest: Option<String>,
+  pub export_content_hash: Option<bool>,
+  pub hash_package_name: Option<bool>,
+  #[napi(ts_type = "\"underscore\" | \"letter\"")]
+  pub ident_start: Option<String>,
+  #[napi(ts_type = "\"replace\" | \"keep\"")]
+  pub invalid_escape: Option<String>,
+  #[napi(ts_type = "\"class\" | \"attribute\"")]
+  pub local_scope: Option<String>,
+  pub local_ident_reg_exp: Option<String>,
 }
 
 impl TryFrom<RawCssModulesConfig> for ModulesConfig {
   type Error = rspack_error::Error;
 
   fn try_from(value: RawCssModulesConfig) -> Result<Self, Self::Error> {
+    let locals_convention = LocalsConvention::from_str(&value.locals_convention)?;
+    let named_export = value.named_export.unwrap_or_default();
+    let es_module = value.es_module.unwrap_or(named_export);
+    if named_export && !es_module {
+      return Err(internal_error!(
+        "css modules namedExport requires esModule to be enabled"
+      ));
+    }
+    let exports_type = match value.exports_type.as_deref() {
+      Some(name) => {
+        let exports_type = ExportsType::from_str(name)?;
+        if value
+          .es_module
+          .is_some_and(|es_module| es_module != exports_type.es_module())
+        {
+          return Err(internal_error!(
+            "css modules esModule contradicts exportsType \"{name}\""
+          ));
+        }
+        exports_type
+      }
+      None if named_export => ExportsType::Namespace,
+      None if es_module => ExportsType::Default,
+      None => ExportsType::CommonJs,
+    };
+    if named_export && exports_type != ExportsType::Namespace {
+      return Err(internal_error!(
+        "css modules namedExport requires exportsType to be \"namespace\""
+      ));
+    }
+    if named_export && !locals_convention.camel_case_only() && !locals_convention.dashes_only() {
+      return Err(internal_error!(
+        "css modules namedExport requires localsConvention to be \"camelCaseOnly\" or \"dashesOnly\""
+      ));
+    }
+    let exports_by_convention = value.exports_by_convention.unwrap_or_default();
+    if exports_by_convention && exports_type == ExportsType::Namespace {
+      return Err(internal_error!(
+        "css modules exportsByConvention can't be used with namedExport or exportsType \"namespace\""
+      ));
+    }
     Ok(Self {
-      locals_convention: LocalsConvention::from_str(&value.locals_convention)?,
-      local_ident_name: LocalIdentName::from(value.local_ident_name),
+      locals_convention,
+      local_ident_name: LocalIdentName::from_str(&value.local_ident_name)?,
+      local_ident_regexp: value
+        .local_ident_reg_exp
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e| {
+          internal_error!("css modules localIdentRegExp is not a valid regular expression: {e}")
+        })?,
+      hash_strategy: value
+        .hash_strategy
+        .as_deref()
+        .map(LocalIdentHashStrategy::from_str)
+        .transpose()?
+        .unwrap_or_default(),
+      hash_prefix: value.hash_prefix,
+      prefix: value.prefix,
+      local_ident_max_length: value.local_ident_max_length.map(|length| length as usize),
+      hash_function: value.hash_function.as_deref().map(Into::into),
+      hash_digest: value.hash_digest.as_deref().map(Into::into),
+      hash_digest_length: value
+        .hash_digest_length
+        .map(|length| match &length {
+          serde_json::Value::String(full) if full == "full" => Ok(FULL_HASH_DIGEST_LENGTH),
+          _ => match length.as_u64() {
+            // Every `[hash]` would be empty, the locals of a file only told apart by the rest of
+            // `localIdentName`, if at all.
+            Some(0) => Err(internal_error!(
+              "css modules hashDigestLength should be at least 1, got 0"
+            )),
+            Some(length) => Ok(length as usize),
+            None => Err(internal_error!(
+              "css modules hashDigestLength should be a number or \"full\", got {length}"
+            )),
+          },
+        })
+        .transpose()?,
+      hash_salt: value.hash_salt.map(|salt| Some(salt).into()),
       exports_only: value.exports_only,
+      exports_type,
+      named_export,
+      export_globals: value.export_globals.unwrap_or_default(),
+      exports_by_convention,
+      composes_separator: value.composes_separator.unwrap_or_else(|| " ".to_string()),
+      export_class_names: value
+        .export_class_names
+        .as_deref()
+        .map(ExportClassNames::from_str)
+        .transpose()?
+        .unwrap_or_default(),
+      lazy_composes: value.lazy_composes.unwrap_or_default(),
+      sort_exports: value.sort_exports.unwrap_or_default(),
+      pathinfo: value.pathinfo.unwrap_or_default(),
+      single_quote: value.single_quote.unwrap_or_default(),
+      freeze_exports: value.freeze_exports.unwrap_or_default(),
+      resilient: value.resilient.unwrap_or_default(),
+      line_ending: value
+        .line_ending
+        .as_deref()
+        .map(LineEnding::from_str)
+        .transpose()?
+        .unwrap_or_default(),
+      trailing_newline: value.trailing_newline.unwrap_or(true),
+      detect_ident_collisions: value.detect_ident_collisions.unwrap_or_default(),
+      pure: value.pure.unwrap_or_default(),
+      ident_manifest: value.ident_manifest,
+      export_content_hash: value.export_content_hash.unwrap_or_default(),
+      hash_package_name: value.hash_package_name.unwrap_or_default(),
+      ident_start: value
+        .ident_start
+        .as_deref()
+        .map(IdentStart::from_str)
+        .transpose()?
+        .unwrap_or_default(),
+      invalid_escape: value
+        .invalid_escape
+        .as_deref()
+        .map(InvalidEscape::from_str)
+        .transpose()?
+        .unwrap_or_default(),
+      local_scope: value
+        .local_scope
+        .as_deref()
+        .map(LocalScope::from_str)
+        .transpose()?
+        .unwrap_or_default(),
+      local_ident_fn: None,
     })
   }
 }
@@ -7,6 +7,8 @@ use rspack_core::{
 pub struct CssComposeDependency {
   id: Option<DependencyId>,
   request: String,
+  /// The `from` of the `composes` as written, kept when a `beforeResolve` hook rewrites `request`.
+  user_request: String,
   span: Option<ErrorSpan>,
 }
 
@@ -14,6 +16,7 @@ impl CssComposeDependency {
   pub fn new(request: String, span: Option<ErrorSpan>) -> Self {
     Self {
       id: None,
+      user_request: request.clone(),
       request,
       span,
     }
@@ -43,7 +46,7 @@ impl ModuleDependency for CssComposeDependency {
   }
 
   fn user_request(&self) -> &str {
-    &self.request
+    &self.user_request
   }
 
   fn span(&self) -> Option<&ErrorSpan> {
@@ -14,17 +14,31 @@ pub struct CssImportDependency {
   span: Option<ErrorSpan>,
   #[allow(unused)]
   ast_path: CssAstPath,
+  /// The media query list of `@import "a.css" print;`.
+  media: Option<String>,
 }
 
 impl CssImportDependency {
-  pub fn new(request: String, span: Option<ErrorSpan>, ast_path: CssAstPath) -> Self {
+  pub fn new(
+    request: String,
+    span: Option<ErrorSpan>,
+    ast_path: CssAstPath,
+    media: Option<String>,
+  ) -> Self {
     Self {
       id: None,
       request,
       span,
       ast_path,
+      media,
     }
   }
+
+  /// The media query list the rules of the imported stylesheet apply under, `None` when they
+  /// always apply.
+  pub fn media(&self) -> Option<&str> {
+    self.media.as_deref()
+  }
 }
 
 impl Dependency for CssImportDependency {
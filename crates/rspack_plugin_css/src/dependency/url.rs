@@ -6,7 +6,7 @@ use rspack_core::{
 };
 use swc_core::css::ast::UrlValue;
 
-use crate::utils::AUTO_PUBLIC_PATH_PLACEHOLDER;
+use crate::utils::{AUTO_PUBLIC_PATH_PLACEHOLDER, CHUNK_RELATIVE_PATH_PLACEHOLDER};
 
 #[derive(Debug, Clone)]
 pub struct CssUrlDependency {
@@ -15,14 +15,22 @@ pub struct CssUrlDependency {
   span: Option<ErrorSpan>,
   #[allow(unused)]
   ast_path: CssAstPath,
+  /// Resolve the url relative to the css chunk instead of `output.publicPath`.
+  relative: bool,
 }
 
 impl CssUrlDependency {
-  pub fn new(request: String, span: Option<ErrorSpan>, ast_path: CssAstPath) -> Self {
+  pub fn new(
+    request: String,
+    span: Option<ErrorSpan>,
+    ast_path: CssAstPath,
+    relative: bool,
+  ) -> Self {
     Self {
       request,
       span,
       ast_path,
+      relative,
       id: None,
     }
   }
@@ -42,6 +50,7 @@ impl CssUrlDependency {
       } else if let Some(filename) = code_gen_result.data.get::<CodeGenerationDataFilename>() {
         let filename = filename.inner();
         let public_path = match &compilation.options.output.public_path {
+          _ if self.relative => CHUNK_RELATIVE_PATH_PLACEHOLDER,
           PublicPath::String(p) => p,
           PublicPath::Auto => AUTO_PUBLIC_PATH_PLACEHOLDER,
         };
@@ -9,3 +9,12 @@ mod utils;
 pub mod visitors;
 
 pub use plugin::CssPlugin;
+pub use utils::{
+  css_modules_export_entries, css_unescape, css_unescape_with, import_media,
+  import_modules_by_request, normalize_lenient_url_with_diagnostics, normalize_url,
+  normalize_url_with_diagnostics, normalize_url_with_options, render_local_ident,
+  replace_auto_public_path, url_to_dependency_request, CodeGenerationDataGlobalClasses,
+  ExportEntry, ExportValue, ExportsCache, LocalHashCollector, LocalIdentCollector,
+  LocalIdentHashParams, ModulesTransformConfig, NormalizeUrlOptions, PublicPath, TransformCounters,
+  TransformCountersSnapshot, CONTENT_HASH_EXPORT,
+};
@@ -1,16 +1,21 @@
 #![allow(clippy::comparison_chain)]
 
-use std::sync::Arc;
+use std::cell::RefCell;
+use std::sync::{
+  atomic::{AtomicBool, Ordering},
+  Arc,
+};
 
 use indexmap::IndexMap;
 use preset_env_base::query::{Query, Targets};
 use rspack_core::{
   ast::css::Ast as CssAst,
   rspack_sources::{
-    MapOptions, RawSource, Source, SourceExt, SourceMap, SourceMapSource, SourceMapSourceOptions,
+    BoxSource, MapOptions, RawSource, Source, SourceExt, SourceMap, SourceMapSource,
+    SourceMapSourceOptions,
   },
-  BuildMetaExportsType, GenerateContext, GenerationResult, Module, ModuleType, ParseContext,
-  ParseResult, ParserAndGenerator, SourceType,
+  BuildMetaExportsType, GenerateContext, GenerationResult, Module, ParseContext, ParseResult,
+  ParserAndGenerator, SourceType,
 };
 use rspack_core::{ModuleAst, ModuleDependency};
 use rspack_error::{
@@ -20,7 +25,9 @@ use rustc_hash::FxHashSet;
 use sugar_path::SugarPath;
 use swc_core::css::visit::VisitMutWithPath;
 use swc_core::{
+  common::{SourceMapper, DUMMY_SP},
   css::{
+    ast::Ident,
     modules::CssClassName,
     parser::parser::ParserConfig,
     prefixer::{options::Options, prefixer},
@@ -35,8 +42,19 @@ use crate::dependency::{
 };
 use crate::plugin::CssConfig;
 use crate::swc_css_compiler::{SwcCssSourceMapGenConfig, SWC_COMPILER};
-use crate::utils::{css_modules_exports_to_string, ModulesTransformConfig};
-use crate::{pxtorem::px_to_rem::px_to_rem, visitors::analyze_dependencies};
+use crate::utils::{
+  clamp_hash_digest_length, css_modules_exports_to_string, exports_cache_key,
+  invalid_named_export_warnings, is_css_modules_request, CodeGenerationDataGlobalClasses,
+  ExportsCache, LocalHashCollector, LocalIdentCollector, LocalIdentHashOptions,
+  ModulesTransformConfig, NormalizeUrlOptions, TransformCounters, CONTENT_HASH_EXPORT,
+};
+use crate::{
+  pxtorem::px_to_rem::px_to_rem,
+  visitors::{
+    analyze_dependencies, collect_global_classes, collect_impure_selectors,
+    collect_undefined_composes, extract_exports, extract_values, scope_locals, CssModuleValue,
+  },
+};
 
 pub(crate) static CSS_MODULE_SOURCE_TYPE_LIST: &[SourceType; 2] =
   &[SourceType::JavaScript, SourceType::Css];
@@ -55,6 +73,22 @@ pub struct CssParserAndGenerator {
   pub config: CssConfig,
   pub meta: Option<String>,
   pub exports: Option<IndexMap<JsWord, Vec<CssClassName>>>,
+  /// Shared by the parsers of a compiler, so a too long `output.hashDigestLength` warns once per
+  /// compilation.
+  pub hash_digest_length_warned: Arc<AtomicBool>,
+  /// Shared by the parsers of a compiler, to detect the locals of its modules renamed alike.
+  pub local_idents: Arc<LocalIdentCollector>,
+  /// Shared by the parsers of a compiler, `None` unless recording, see [LocalHashCollector].
+  pub local_hashes: Option<Arc<LocalHashCollector>>,
+  /// Shared by the generators of a compiler, to reuse the exports code of unchanged modules.
+  pub exports_cache: Arc<ExportsCache>,
+  /// See [exports_cache_key], `None` unless the module is a css module.
+  pub exports_cache_key: Option<u64>,
+  /// See [CodeGenerationDataGlobalClasses], `None` unless the module is a css module.
+  pub global_classes: Option<Vec<JsWord>>,
+  /// Shared by the parsers and generators of a compilation, `None` unless counting, see
+  /// [TransformCounters].
+  pub counters: Option<Arc<TransformCounters>>,
 }
 
 impl CssParserAndGenerator {
@@ -104,21 +138,26 @@ impl ParserAndGenerator for CssParserAndGenerator {
       build_info,
       build_meta,
       code_generation_dependencies,
+      module_identifier,
       ..
     } = parse_context;
     build_info.strict = true;
-    // here different webpack
-    build_meta.exports_type = BuildMetaExportsType::Default;
+    let css_modules = is_css_modules_request(module_type, resource_data.resource_query.as_deref());
+    build_meta.exports_type = if css_modules && self.config.modules.exports_type.es_module() {
+      BuildMetaExportsType::Namespace
+    } else {
+      // here different webpack
+      BuildMetaExportsType::Default
+    };
     let cm: Arc<swc_core::common::SourceMap> = Default::default();
-    let content = source.source().to_string();
-    let css_modules = matches!(module_type, ModuleType::CssModule);
+    let content = source.source();
     let TWithDiagnosticArray {
       inner: mut stylesheet,
       mut diagnostic,
     } = SWC_COMPILER.parse_file(
       cm.clone(),
       &parse_context.resource_data.resource_path.to_string_lossy(),
-      content,
+      content.to_string(),
       ParserConfig {
         css_modules,
         legacy_ie: true,
@@ -137,19 +176,213 @@ impl ParserAndGenerator for CssParserAndGenerator {
     }
 
     let locals = if css_modules {
+      let globals = collect_global_classes(&stylesheet);
+      for class in collect_undefined_composes(&stylesheet) {
+        let loc = cm.lookup_char_pos(class.span.lo);
+        diagnostic.push(Diagnostic::warn(
+          "CSS Modules".to_string(),
+          format!(
+            "file: {}:{}:{} `composes: {}` references a class that is not defined in the file, it is ignored.",
+            resource_data.resource_path.display(),
+            loc.line,
+            loc.col_display + 1,
+            class.value
+          ),
+          0,
+          0,
+        ));
+      }
+      // Before the transform strips the `:global`s telling which classes aren't scoped.
+      if self.config.modules.pure {
+        for span in collect_impure_selectors(&stylesheet) {
+          let loc = cm.lookup_char_pos(span.lo);
+          diagnostic.push(Diagnostic::error(
+            "CSS Modules".to_string(),
+            format!(
+              "file: {}:{}:{} selector `{}` is not pure, in pure mode every selector of a css module needs a local class or id.",
+              resource_data.resource_path.display(),
+              loc.line,
+              loc.col_display + 1,
+              cm.span_to_snippet(span).unwrap_or_default(),
+            ),
+            0,
+            0,
+          ));
+        }
+      }
       let filename = &resource_data
         .resource_path
         .relative(&compiler_options.context);
-      let result = swc_core::css::modules::compile(
-        &mut stylesheet,
-        ModulesTransformConfig::new(
-          filename,
-          &self.config.modules.local_ident_name,
-          &compiler_options.output,
-        ),
+      let hash_options = LocalIdentHashOptions::new(&self.config.modules, &compiler_options.output);
+      let (hash_digest_length, clamped) = clamp_hash_digest_length(
+        hash_options.hash_function,
+        hash_options.hash_digest,
+        hash_options.hash_digest_length,
+      );
+      if clamped && !self.hash_digest_length_warned.swap(true, Ordering::Relaxed) {
+        let message = if hash_options.hash_digest_length == 0 {
+          "hashDigestLength 0 would leave css modules hashes empty, they are 1 character long instead.".to_string()
+        } else {
+          format!(
+            "hashDigestLength {} is longer than the digest, css modules hashes are clamped to {hash_digest_length} characters.",
+            hash_options.hash_digest_length
+          )
+        };
+        diagnostic.push(Diagnostic::warn("CSS Modules".to_string(), message, 0, 0));
+      }
+      let recorded_hashes = RefCell::new(IndexMap::default());
+      let mut config = ModulesTransformConfig::new(
+        filename,
+        &content,
+        &self.config.modules,
+        &compiler_options.output,
+      )
+      .counters(self.counters.as_deref())
+      .package_name(
+        resource_data
+          .resource_description
+          .as_ref()
+          .and_then(|description| description.data().raw().get("name"))
+          .and_then(|name| name.as_str()),
       );
+      if self.local_hashes.is_some() {
+        config = config.record_hashes(&recorded_hashes);
+      }
+      let scope_id = config.scope_id();
+      let content_hash = config.content_hash().map(ToString::to_string);
+      self.global_classes = Some(globals.clone());
+      let result = swc_core::css::modules::compile(&mut stylesheet, config);
       let mut exports: IndexMap<JsWord, _> = result.renamed.into_iter().collect();
+      if let Some(local_hashes) = &self.local_hashes {
+        local_hashes.record(module_identifier, recorded_hashes.into_inner());
+      }
+      if let Some(scope_id) = &scope_id {
+        scope_locals(&mut stylesheet, &mut exports, scope_id);
+      }
+      // Scoped by attribute, the locals of different files are meant to share their names.
+      if (self.config.modules.detect_ident_collisions
+        || self.config.modules.ident_manifest.is_some())
+        && scope_id.is_none()
+      {
+        // The ident of a local comes first, before the classes it composes.
+        let idents = exports
+          .iter()
+          .filter_map(|(local, names)| match names.first() {
+            Some(CssClassName::Local { name }) => Some((local.clone(), name.value.clone())),
+            _ => None,
+          })
+          .collect();
+        self.local_idents.record(module_identifier, idents);
+      }
+      for (key, value) in extract_exports(&mut stylesheet, &cm, self.config.modules.invalid_escape)
+      {
+        let key = JsWord::from(key);
+        if exports.contains_key(&key) {
+          diagnostic.push(Diagnostic::warn(
+            "CSS Modules".to_string(),
+            format!(
+              "file: {} `:export {{ {key} }}` takes precedence over the class with the same name.",
+              resource_data.resource_path.display()
+            ),
+            0,
+            0,
+          ));
+        }
+        exports.insert(
+          key,
+          vec![CssClassName::Global {
+            name: Ident {
+              span: DUMMY_SP,
+              value: value.into(),
+              raw: None,
+            },
+          }],
+        );
+      }
+      for (key, value) in extract_values(&mut stylesheet, &cm) {
+        let key = JsWord::from(key);
+        if exports.contains_key(&key) {
+          diagnostic.push(Diagnostic::warn(
+            "CSS Modules".to_string(),
+            format!(
+              "file: {} `@value {key}` is not exported, a class with the same name takes precedence.",
+              resource_data.resource_path.display()
+            ),
+            0,
+            0,
+          ));
+          continue;
+        }
+        let value = match value {
+          CssModuleValue::Local(value) => CssClassName::Global {
+            name: Ident {
+              span: DUMMY_SP,
+              value: value.into(),
+              raw: None,
+            },
+          },
+          CssModuleValue::Import { name, from } => CssClassName::Import {
+            name: Ident {
+              span: DUMMY_SP,
+              value: name.into(),
+              raw: None,
+            },
+            from: from.into(),
+          },
+        };
+        exports.insert(key, vec![value]);
+      }
+      for name in globals
+        .iter()
+        .filter(|_| self.config.modules.export_globals)
+        .cloned()
+      {
+        exports.entry(name.clone()).or_insert_with(|| {
+          vec![CssClassName::Global {
+            name: Ident {
+              span: DUMMY_SP,
+              value: name,
+              raw: None,
+            },
+          }]
+        });
+      }
+      if let Some(content_hash) = content_hash {
+        if exports.contains_key(&JsWord::from(CONTENT_HASH_EXPORT)) {
+          diagnostic.push(Diagnostic::warn(
+            "CSS Modules".to_string(),
+            format!(
+              "file: {} `{CONTENT_HASH_EXPORT}` is the content hash of the file, the class with the same name isn't exported.",
+              resource_data.resource_path.display()
+            ),
+            0,
+            0,
+          ));
+        }
+        exports.insert(
+          CONTENT_HASH_EXPORT.into(),
+          vec![CssClassName::Global {
+            name: Ident {
+              span: DUMMY_SP,
+              value: content_hash.into(),
+              raw: None,
+            },
+          }],
+        );
+      }
       exports.sort_keys();
+      self.exports_cache_key = Some(exports_cache_key(
+        &resource_data.resource,
+        &content,
+        &exports,
+      ));
+      if self.config.modules.named_export {
+        diagnostic.extend(invalid_named_export_warnings(
+          &exports,
+          &self.config.modules.locals_convention,
+          &resource_data.resource_path,
+        ));
+      }
       Some(exports)
     } else {
       None
@@ -157,8 +390,18 @@ impl ParserAndGenerator for CssParserAndGenerator {
 
     let mut dependencies = analyze_dependencies(
       &mut stylesheet,
+      resource_data
+        .resource_path
+        .parent()
+        .unwrap_or(&resource_data.resource_path),
       code_generation_dependencies,
       &mut diagnostic,
+      self.config.relative_urls,
+      NormalizeUrlOptions {
+        lenient: self.config.lenient_urls,
+        percent_decode: !self.config.keep_url_percent_escapes,
+      },
+      self.counters.as_deref(),
     );
 
     let  dependencies = if let Some(locals) = &locals && !locals.is_empty() {
@@ -180,6 +423,10 @@ impl ParserAndGenerator for CssParserAndGenerator {
     };
 
     self.meta = additional_data.and_then(|data| if data.is_empty() { None } else { Some(data) });
+    if let Some(locals) = &locals && locals.is_empty() {
+      // No JS is generated for a css module without exports, like for plain css.
+      build_meta.exports_type = BuildMetaExportsType::Default;
+    }
     self.exports = locals;
 
     if self.exports.is_some() && let Some(meta) = &self.meta && serde_json::from_str::<RspackPostcssModules>(meta).is_ok() {
@@ -204,6 +451,11 @@ impl ParserAndGenerator for CssParserAndGenerator {
     module: &dyn rspack_core::Module,
     generate_context: &mut GenerateContext,
   ) -> Result<rspack_core::GenerationResult> {
+    if let Some(global_classes) = &self.global_classes {
+      generate_context
+        .data
+        .insert(CodeGenerationDataGlobalClasses::new(global_classes.clone()));
+    }
     let result = match generate_context.requested_source_type {
       SourceType::Css => {
         let devtool = &generate_context.compilation.options.devtool;
@@ -251,50 +503,59 @@ impl ParserAndGenerator for CssParserAndGenerator {
           },
         )?;
         if let Some(source_map) = source_map {
-          let source = SourceMapSource::new(SourceMapSourceOptions {
-            value: code,
-            name: module.try_as_normal_module()?.user_request().to_string(),
-            source_map: SourceMap::from_slice(&source_map)
-              .map_err(|e| internal_error!(e.to_string()))?,
-            // Safety: original source exists in code generation
-            original_source: Some(
-              module
-                .original_source()
-                .expect("Failed to get original source, please file an issue.")
-                .source()
-                .to_string(),
-            ),
-            // Safety: original source exists in code generation
-            inner_source_map: module
-              .original_source()
-              .expect("Failed to get original source, please file an issue.")
-              .map(&MapOptions::default()),
-            remove_original_source: false,
-          })
-          .boxed();
-          Ok(source)
+          with_source_map(code, &source_map, module)
         } else {
           Ok(RawSource::from(code).boxed())
         }
       }
       SourceType::JavaScript => {
-        let locals = if let Some(exports) = &self.exports {
+        let devtool = &generate_context.compilation.options.devtool;
+        let cm = ast_or_source
+          .as_ast()
+          .and_then(|ast| ast.as_css())
+          .filter(|_| devtool.source_map())
+          .map(|ast| ast.get_context().source_map.clone());
+        let mut mappings = cm.is_some().then(Vec::new);
+        let mut locals = if let Some(exports) = &self.exports {
+          if let Some(counters) = &self.counters {
+            counters.count_composed_imports(exports);
+          }
           css_modules_exports_to_string(
             exports,
             module,
             generate_context.compilation,
-            &self.config.modules.locals_convention,
+            &self.config.modules,
+            generate_context.runtime_requirements,
+            mappings.as_mut(),
+            self
+              .exports_cache_key
+              .map(|key| (&*self.exports_cache, key)),
           )?
         } else if let Some(meta) = &self.meta
           && let Ok(meta) = serde_json::from_str::<RspackPostcssModules>(meta)
         {
           format!("module.exports = {};\n", meta.rspack_postcss_modules)
-        } else if generate_context.compilation.options.dev_server.hot  {
-          "module.hot.accept();".to_string()
         } else {
           "".to_string()
         };
-        Ok(RawSource::from(locals).boxed())
+        // Nothing is exported, e.g. plain css or css modules of globals only.
+        if locals.is_empty() && generate_context.compilation.options.dev_server.hot {
+          locals = "module.hot.accept();".to_string();
+        }
+        if let Some(cm) = cm && let Some(mappings) = &mut mappings && !mappings.is_empty() {
+          let source_map = SWC_COMPILER.build_source_map(
+            cm,
+            mappings,
+            SwcCssSourceMapGenConfig {
+              enable: true,
+              inline_sources_content: !devtool.no_sources(),
+              emit_columns: !devtool.cheap(),
+            },
+          )?;
+          with_source_map(locals, &source_map, module)
+        } else {
+          Ok(RawSource::from(locals).boxed())
+        }
       }
       _ => Err(internal_error!(
         "Unsupported source type: {:?}",
@@ -307,3 +568,28 @@ impl ParserAndGenerator for CssParserAndGenerator {
     })
   }
 }
+
+fn with_source_map(code: String, source_map: &[u8], module: &dyn Module) -> Result<BoxSource> {
+  Ok(
+    SourceMapSource::new(SourceMapSourceOptions {
+      value: code,
+      name: module.try_as_normal_module()?.user_request().to_string(),
+      source_map: SourceMap::from_slice(source_map).map_err(|e| internal_error!(e.to_string()))?,
+      // Safety: original source exists in code generation
+      original_source: Some(
+        module
+          .original_source()
+          .expect("Failed to get original source, please file an issue.")
+          .source()
+          .to_string(),
+      ),
+      // Safety: original source exists in code generation
+      inner_source_map: module
+        .original_source()
+        .expect("Failed to get original source, please file an issue.")
+        .map(&MapOptions::default()),
+      remove_original_source: false,
+    })
+    .boxed(),
+  )
+}
@@ -1,14 +1,15 @@
 #![allow(clippy::comparison_chain)]
 
 use std::hash::Hash;
+use std::sync::atomic::Ordering;
 
 use rayon::prelude::*;
 use rspack_core::rspack_sources::ReplaceSource;
 use rspack_core::{
   get_css_chunk_filename_template,
   rspack_sources::{ConcatSource, MapOptions, RawSource, Source, SourceExt},
-  Chunk, ChunkKind, Module, ModuleType, ParserAndGenerator, PathData, Plugin, RenderManifestEntry,
-  SourceType,
+  Chunk, ChunkKind, CompilationAsset, Module, ModuleType, ParserAndGenerator, PathData, Plugin,
+  RenderManifestEntry, SourceType,
 };
 use rspack_core::{Compilation, LibIdentOptions};
 use rspack_error::Result;
@@ -16,7 +17,11 @@ use rspack_hash::RspackHash;
 
 use crate::parser_and_generator::CssParserAndGenerator;
 use crate::swc_css_compiler::{SwcCssSourceMapGenConfig, SWC_COMPILER};
-use crate::utils::AUTO_PUBLIC_PATH_PLACEHOLDER_REGEX;
+use crate::utils::{
+  compose_cycle_warnings, composes_from_non_css_module_errors, import_media,
+  local_ident_collision_errors, local_ident_manifest, missing_composed_module_warnings,
+  public_path_replacements, PublicPath,
+};
 use crate::CssPlugin;
 
 struct CssModuleDebugInfo<'a> {
@@ -42,8 +47,12 @@ impl CssPlugin {
           .map(|result| result.ast_or_source.clone().try_into_source())
           .transpose();
 
-        module_source
-          .map(|source| source.map(|source| (CssModuleDebugInfo { module: *module }, source)))
+        module_source.map(|source| {
+          source.map(|source| {
+            let media = import_media(compilation, *module);
+            (CssModuleDebugInfo { module: *module }, media, source)
+          })
+        })
       })
       .collect::<Result<Vec<_>>>()?;
 
@@ -55,11 +64,18 @@ impl CssPlugin {
       .flatten()
       .fold(
         ConcatSource::default,
-        |mut acc, (debug_info, cur_source)| {
+        |mut acc, (debug_info, media, cur_source)| {
           let (start, end) = Self::render_module_debug_info(compilation, &debug_info);
           acc.add(start);
+          // The `@import` conditions are dropped with the `@import`, they wrap the rules instead.
+          for media in &media {
+            acc.add(RawSource::from(format!("@media {media} {{\n")));
+          }
           acc.add(cur_source);
           acc.add(RawSource::from("\n"));
+          for _ in &media {
+            acc.add(RawSource::from("}\n"));
+          }
           acc.add(end);
           acc
         },
@@ -116,11 +132,23 @@ impl Plugin for CssPlugin {
 
   fn apply(&self, ctx: rspack_core::PluginContext<&mut rspack_core::ApplyContext>) -> Result<()> {
     let config = self.config.clone();
+    let hash_digest_length_warned = self.hash_digest_length_warned.clone();
+    let local_idents = self.local_idents.clone();
+    let local_hashes = config.record_hashes.then(|| self.local_hashes.clone());
+    let exports_cache = self.exports_cache.clone();
+    let counters = config.debug_counters.then(|| self.counters.clone());
     let builder = move || {
       Box::new(CssParserAndGenerator {
         config: config.clone(),
         meta: None,
         exports: None,
+        hash_digest_length_warned: hash_digest_length_warned.clone(),
+        local_idents: local_idents.clone(),
+        local_hashes: local_hashes.clone(),
+        exports_cache: exports_cache.clone(),
+        exports_cache_key: None,
+        global_classes: None,
+        counters: counters.clone(),
       }) as Box<dyn ParserAndGenerator>
     };
 
@@ -134,6 +162,32 @@ impl Plugin for CssPlugin {
     Ok(())
   }
 
+  async fn this_compilation(
+    &self,
+    _args: rspack_core::ThisCompilationArgs<'_>,
+  ) -> rspack_core::PluginThisCompilationHookOutput {
+    self.counters.reset();
+    self
+      .hash_digest_length_warned
+      .store(false, Ordering::Relaxed);
+    Ok(())
+  }
+
+  async fn after_compile(&self, _compilation: &mut Compilation) -> Result<()> {
+    if self.config.debug_counters {
+      let counters = self.counters.snapshot();
+      tracing::info_span!(
+        "css_modules_counters",
+        new_name_for = counters.new_name_for,
+        fast_urls = counters.fast_urls,
+        slow_urls = counters.slow_urls,
+        composed_imports = counters.composed_imports,
+      )
+      .in_scope(|| tracing::info!("{counters:?}"));
+    }
+    Ok(())
+  }
+
   async fn content_hash(
     &self,
     _ctx: rspack_core::PluginContext,
@@ -218,21 +272,15 @@ impl Plugin for CssPlugin {
         .runtime(&chunk.runtime),
     );
 
-    let content = source.source();
-    let auto_public_path_matches: Vec<_> = AUTO_PUBLIC_PATH_PLACEHOLDER_REGEX
-      .find_iter(&content)
-      .map(|mat| (mat.start(), mat.end()))
-      .collect();
-    let source = if !auto_public_path_matches.is_empty() {
+    let replacements = public_path_replacements(
+      &source.source(),
+      &PublicPath::from(&compilation.options.output.public_path),
+      &output_path,
+    );
+    let source = if !replacements.is_empty() {
       let mut replace = ReplaceSource::new(source);
-      for (start, end) in auto_public_path_matches {
-        let relative = args
-          .compilation
-          .options
-          .output
-          .public_path
-          .render(args.compilation, &output_path);
-        replace.replace(start as u32, end as u32, &relative, None);
+      for (start, end, path) in replacements {
+        replace.replace(start as u32, end as u32, &path, None);
       }
       replace.boxed()
     } else {
@@ -245,6 +293,49 @@ impl Plugin for CssPlugin {
     )])
   }
 
+  async fn finish_modules(&self, compilation: &mut Compilation) -> Result<()> {
+    let errors = composes_from_non_css_module_errors(compilation);
+    compilation.push_batch_diagnostic(errors);
+    let warnings = compose_cycle_warnings(compilation);
+    compilation.push_batch_diagnostic(warnings);
+    if self.config.modules.resilient {
+      let warnings = missing_composed_module_warnings(compilation);
+      compilation.push_batch_diagnostic(warnings);
+    }
+    let module_graph = &compilation.module_graph;
+    self
+      .local_idents
+      .retain(|module| module_graph.module_by_identifier(module).is_some());
+    self
+      .local_hashes
+      .retain(|module| module_graph.module_by_identifier(module).is_some());
+    self
+      .exports_cache
+      .retain(|module| module_graph.module_by_identifier(module).is_some());
+    if self.config.modules.detect_ident_collisions {
+      let errors = local_ident_collision_errors(compilation, &self.local_idents);
+      compilation.push_batch_diagnostic(errors);
+    }
+    Ok(())
+  }
+
+  async fn process_assets_stage_additional(
+    &self,
+    _ctx: rspack_core::PluginContext,
+    args: rspack_core::ProcessAssetsArgs<'_>,
+  ) -> rspack_core::PluginProcessAssetsOutput {
+    let Some(filename) = &self.config.modules.ident_manifest else {
+      return Ok(());
+    };
+    let compilation = args.compilation;
+    let manifest = local_ident_manifest(compilation, &self.local_idents)?;
+    compilation.emit_asset(
+      filename.clone(),
+      CompilationAsset::from(RawSource::from(manifest).boxed()),
+    );
+    Ok(())
+  }
+
   async fn process_assets_stage_optimize_size(
     &self,
     _ctx: rspack_core::PluginContext,
@@ -1,25 +1,67 @@
 #![allow(clippy::comparison_chain)]
 mod impl_plugin_for_css_plugin;
+use std::borrow::Cow;
 use std::cmp::{self, Reverse};
 use std::hash::Hash;
 use std::str::FromStr;
+use std::sync::{atomic::AtomicBool, Arc};
 
 use anyhow::bail;
 use bitflags::bitflags;
+use derivative::Derivative;
+use heck::{ToKebabCase, ToLowerCamelCase};
+use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use rspack_core::Filename;
-use rspack_core::{Chunk, ChunkGraph, Compilation, Module, ModuleGraph, PathData, SourceType};
+use rspack_core::{
+  Chunk, ChunkGraph, Compilation, Module, ModuleGraph, ModuleIdentifier, PathData, SourceType,
+};
+use rspack_core::{Filename, HashDigest, HashFunction, HashSalt, NAME_PLACEHOLDER};
 use rspack_identifier::IdentifierSet;
+use swc_core::ecma::atoms::JsWord;
 
 use crate::pxtorem::options::PxToRemOptions;
+use crate::utils::{
+  ExportsCache, LocalHashCollector, LocalIdentCollector, TransformCounters,
+  TransformCountersSnapshot,
+};
 
 static ESCAPE_LOCAL_IDENT_REGEX: Lazy<Regex> =
-  Lazy::new(|| Regex::new(r#"[<>:"/\\|?*\.]"#).expect("Invalid regex"));
+  Lazy::new(|| Regex::new(r#"[<>:"/\\|?*\.\s]"#).expect("Invalid regex"));
+
+static BASE62_HASH_PLACEHOLDER_REGEX: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"\[hash:base62(?::(\d+))?\]").expect("Invalid regex"));
+
+static REGEXP_GROUP_PLACEHOLDER_REGEX: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"\[(\d+)\]").expect("Invalid regex"));
+
+/// A `[token]`, or a `[\token\]` kept literally as `[token]`.
+static LOCAL_IDENT_NAME_TOKEN_REGEX: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"\[\\([^\]]*)\\\]|\[([^\[\]]*)\]").expect("Invalid regex"));
+
+static SUPPORTED_LOCAL_IDENT_NAME_TOKEN_REGEX: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(
+    r"^(?:file|base|name|path|ext|query|fragment|folder|local|(?:hash|contenthash|fullhash)(?::\d*)?|hash:base62(?::\d+)?|\d+)$",
+  )
+  .expect("Invalid regex")
+});
+
+const SUPPORTED_LOCAL_IDENT_NAME_TOKENS: &str = "[file], [base], [name], [path], [ext], [query], \
+  [fragment], [folder], [local], [hash], [hash:<length>], [contenthash], [contenthash:<length>], \
+  [fullhash], [hash:base62], [hash:base62:<length>] and the [<n>] groups of localIdentRegExp";
+
+pub const FOLDER_PLACEHOLDER: &str = "[folder]";
+pub const LOCAL_PLACEHOLDER: &str = "[local]";
 
 #[derive(Debug)]
 pub struct CssPlugin {
   config: CssConfig,
+  /// Reset by every compilation, so a too long `output.hashDigestLength` warns in each build.
+  hash_digest_length_warned: Arc<AtomicBool>,
+  local_idents: Arc<LocalIdentCollector>,
+  local_hashes: Arc<LocalHashCollector>,
+  exports_cache: Arc<ExportsCache>,
+  counters: Arc<TransformCounters>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -27,36 +69,503 @@ pub struct PostcssConfig {
   pub pxtorem: Option<PxToRemOptions>,
 }
 
-#[derive(Debug, Clone)]
+/// Override the generated local ident, like css-loader's `getLocalIdent`.
+///
+/// Returning `None` falls back to rendering `localIdentName`.
+pub type LocalIdentFn = Arc<dyn Fn(LocalIdentContext) -> Option<String> + Send + Sync>;
+
+pub struct LocalIdentContext<'a> {
+  /// Resource path relative to the context.
+  pub filename: &'a str,
+  pub local: &'a str,
+  /// The hash `[hash]` would be replaced with.
+  pub hash: &'a str,
+}
+
+/// The `hashDigestLength: "full"` of css modules, local idents get the entire digest.
+pub const FULL_HASH_DIGEST_LENGTH: usize = usize::MAX;
+
+#[derive(Derivative)]
+#[derivative(Debug, Clone)]
 pub struct ModulesConfig {
   pub locals_convention: LocalsConvention,
   pub local_ident_name: LocalIdentName,
+  /// Matched against the resource path relative to the context, with `/` separators, its capture
+  /// groups replace the `[1]`, `[2]`, ... of `local_ident_name`, like css-loader's
+  /// `localIdentRegExp`. A group that didn't match, or all of them if the path doesn't, is empty.
+  pub local_ident_regexp: Option<Regex>,
+  pub hash_strategy: LocalIdentHashStrategy,
+  /// Mixed into the hash of local idents only, so separately built apps loaded on the same page
+  /// don't generate the same class names. Unlike `output.hashSalt`, other hashes are unaffected.
+  pub hash_prefix: Option<String>,
+  /// Also mix the `name` of the `package.json` closest to a file into the hash of its locals, so
+  /// files at the same path in different packages of a monorepo don't collide. Files outside of a
+  /// package, or of one without a name, are hashed as without it.
+  pub hash_package_name: bool,
+  /// Prepended to every local ident, whether rendered from `localIdentName` or returned by
+  /// `local_ident_fn`, e.g. to namespace the classes of a component library.
+  pub prefix: Option<String>,
+  /// Cut local idents longer than this many characters. The text around the hash is cut from the
+  /// end, the hash is always kept whole so idents stay unique. An ident without a hash ends with
+  /// `_` and the hash once cut. To find the hash, an ident to cut is rendered again with another
+  /// one, calling `local_ident_fn` twice.
+  pub local_ident_max_length: Option<usize>,
+  /// Hash options of local idents, `output`'s apply to those left unset.
+  pub hash_function: Option<HashFunction>,
+  pub hash_digest: Option<HashDigest>,
+  /// [FULL_HASH_DIGEST_LENGTH] keeps the whole digest, whatever its length.
+  pub hash_digest_length: Option<usize>,
+  pub hash_salt: Option<HashSalt>,
+  /// Only generate the JS of the locals, composed classes included, and no css. Like css-loader's
+  /// `exportOnlyLocals`, for server rendering or when another build emits the css.
   pub exports_only: bool,
+  /// Emit the locals as `module.exports`, the default export of an ES module or its namespace.
+  pub exports_type: ExportsType,
+  /// Emit the locals as named exports only, leaving out the keys that aren't valid identifiers.
+  /// Requires the `Namespace` exports type and a `camelCaseOnly` or `dashesOnly` locals convention.
+  pub named_export: bool,
+  /// Export the class names of `:global` selectors alongside the locals, mapped to themselves.
+  pub export_globals: bool,
+  /// Error for every selector that selects no local class or id, e.g. `div` or `:global(.a)`, so
+  /// each rule of a css module is scoped to it. Like css-loader's `pure` mode.
+  pub pure: bool,
+  /// Export the keys of each locals convention in an object of their own, named after the
+  /// convention, e.g. `exports.asIs["foo-bar"]` and `exports.camelCase.fooBar` with `camelCase`,
+  /// so the keys of different conventions can't collide. Can't be used with `named_export`.
+  pub exports_by_convention: bool,
+  /// Separator between the class names of a local and the ones it `composes`, a space by default.
+  pub composes_separator: String,
+  pub export_class_names: ExportClassNames,
+  /// Export the locals composing classes of other css modules as getters, so those modules are
+  /// only required once the local is read. Named exports are always getters.
+  pub lazy_composes: bool,
+  /// Emit the export keys in alphabetical order, including the ones added by the locals
+  /// convention, so the generated code doesn't change when selectors are reordered.
+  pub sort_exports: bool,
+  /// Prefix the exports with a comment naming the css module they belong to, e.g. to find which
+  /// file produced them in a bundle. Ignored outside of development mode.
+  pub pathinfo: bool,
+  /// Quote the strings of the generated exports with `'` instead of `"`.
+  pub single_quote: bool,
+  /// Wrap the exported object in `Object.freeze`, so assigning one of its keys throws in strict
+  /// mode. With the `Default` exports type the default export is frozen, named exports are read-only as is.
+  pub freeze_exports: bool,
+  /// Keep generating the exports of a css module `composes` classes from a file that can't be
+  /// resolved, leaving those classes out with a warning, e.g. so a dev server recovers once the
+  /// file is created. Otherwise requiring the missing file throws.
+  pub resilient: bool,
+  /// Line ending of the generated exports code.
+  pub line_ending: LineEnding,
+  /// End the generated exports code with a line ending, on by default. Off, the exports can be
+  /// concatenated byte for byte with other generated code.
+  pub trailing_newline: bool,
+  /// Error when the locals of different files, or of the same one, are renamed to the same ident,
+  /// e.g. with a `hashDigestLength` too short to tell their hashes apart.
+  pub detect_ident_collisions: bool,
+  /// Emit an asset of this filename mapping the path of each css module to the idents its locals
+  /// are renamed to, as JSON, see [LocalIdentCollector::manifest].
+  pub ident_manifest: Option<String>,
+  /// Also export the content hash of the file under the reserved `__hash__` key, e.g. for cache
+  /// busting, see [ModulesTransformConfig::content_hash]. It shadows a class of the same name.
+  ///
+  /// [ModulesTransformConfig::content_hash]: crate::ModulesTransformConfig::content_hash
+  pub export_content_hash: bool,
+  pub ident_start: IdentStart,
+  pub invalid_escape: InvalidEscape,
+  pub local_scope: LocalScope,
+  #[derivative(Debug = "ignore")]
+  pub local_ident_fn: Option<LocalIdentFn>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LocalIdentName(Filename);
 
 impl LocalIdentName {
+  pub fn template(&self) -> &str {
+    self.0.template()
+  }
+
+  pub fn has_content_hash_placeholder(&self) -> bool {
+    self.0.template().contains("[contenthash")
+  }
+
   pub fn render(&self, options: LocalIdentNameRenderOptions) -> String {
-    let mut s = self.0.render(options.path_data, None);
+    let folder = options.path_data.filename.map(folder_name);
+    let captures = options
+      .local_ident_regexp
+      .map(|regexp| regexp.captures(options.path_data.filename.unwrap_or_default()));
+    let mut s = match options.path_data.filename.and_then(index_folder_name) {
+      Some(folder) if self.0.template().contains(NAME_PLACEHOLDER) => {
+        Filename::from(self.0.template().replace(NAME_PLACEHOLDER, folder))
+          .render(options.path_data, None)
+      }
+      _ => self.0.render(options.path_data, None),
+    };
+    if let Some(captures) = captures {
+      s = REGEXP_GROUP_PLACEHOLDER_REGEX
+        .replace_all(&s, |caps: &regex::Captures| {
+          caps[1]
+            .parse::<usize>()
+            .ok()
+            .and_then(|group| captures.as_ref()?.get(group))
+            .map_or("", |group| group.as_str())
+            .to_string()
+        })
+        .into_owned();
+    }
+    if let Some(hash) = options.base62_hash {
+      s = BASE62_HASH_PLACEHOLDER_REGEX
+        .replace_all(&s, |caps: &regex::Captures| {
+          let len = caps
+            .get(1)
+            .and_then(|len| len.as_str().parse().ok())
+            .unwrap_or(hash.len());
+          &hash[..hash.len().min(len)]
+        })
+        .into_owned();
+    }
+    if let Some(folder) = folder {
+      s = s.replace(FOLDER_PLACEHOLDER, folder);
+    }
     if let Some(local) = options.local {
-      s = s.replace("[local]", local);
+      s = s.replace(LOCAL_PLACEHOLDER, local);
+    }
+    if s.contains("[\\") {
+      s = LOCAL_IDENT_NAME_TOKEN_REGEX
+        .replace_all(&s, |caps: &regex::Captures| match caps.get(1) {
+          Some(escaped) => format!("[{}]", escaped.as_str()),
+          None => caps[0].to_string(),
+        })
+        .into_owned();
     }
-    s = ESCAPE_LOCAL_IDENT_REGEX.replace_all(&s, "-").into_owned();
-    s
+    escape_local_ident(&s, options.ident_start)
   }
 }
 
+/// Make `s` a legal css identifier.
+pub(crate) fn escape_local_ident(s: &str, ident_start: IdentStart) -> String {
+  let mut s = ESCAPE_LOCAL_IDENT_REGEX.replace_all(s, "-").into_owned();
+  // An identifier can't start with a digit, or a hyphen followed by a digit.
+  let hyphens = s.len() - s.trim_start_matches('-').len();
+  if is_leading_digit(s.as_bytes().get(hyphens)) && !s.starts_with("--") {
+    match ident_start {
+      IdentStart::Underscore => s.insert(0, '_'),
+      IdentStart::Letter => {
+        let letter = digit_to_letter(s.as_bytes()[hyphens]);
+        s.replace_range(hyphens..hyphens + 1, letter.encode_utf8(&mut [0; 4]));
+      }
+    }
+  }
+  s
+}
+
 impl From<String> for LocalIdentName {
   fn from(value: String) -> Self {
     Self(Filename::from(value))
   }
 }
 
+impl FromStr for LocalIdentName {
+  type Err = anyhow::Error;
+
+  /// Like `From<String>`, but rejects the tokens that would be left as is in class names.
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    for caps in LOCAL_IDENT_NAME_TOKEN_REGEX.captures_iter(s) {
+      if let Some(token) = caps.get(2)
+        && !SUPPORTED_LOCAL_IDENT_NAME_TOKEN_REGEX.is_match(token.as_str())
+      {
+        bail!(
+          "css modules localIdentName `{s}` has an unsupported token `[{}]`, supported tokens are {SUPPORTED_LOCAL_IDENT_NAME_TOKENS}",
+          token.as_str()
+        );
+      }
+    }
+    Ok(Self::from(s.to_string()))
+  }
+}
+
+fn is_leading_digit(byte: Option<&u8>) -> bool {
+  byte.map_or(false, |b| b.is_ascii_digit())
+}
+
+/// Name of the directory directly containing `filename`, align with css-loader's `[folder]`.
+///
+/// Both `/` and `\\` are treated as separators, so Windows-style paths yield the same result.
+/// Files without a parent directory produce an empty string.
+fn folder_name(filename: &str) -> &str {
+  let mut segments = filename.rsplit(['/', '\\']);
+  // skip the basename
+  segments.next();
+  segments.next().unwrap_or_default()
+}
+
+/// The `[name]` of an index file, its folder like webpack names them, e.g. `button` for
+/// `src/button/index.module.css`. `None` for other files and index files without a folder, named
+/// after the file itself.
+fn index_folder_name(filename: &str) -> Option<&str> {
+  let basename = filename.rsplit(['/', '\\']).next()?;
+  let folder = folder_name(filename);
+  (basename.split('.').next() == Some("index") && !folder.is_empty()).then_some(folder)
+}
+
 pub struct LocalIdentNameRenderOptions<'a> {
   pub path_data: PathData<'a>,
   pub local: Option<&'a str>,
+  /// Replaces `[hash:base62]` and `[hash:base62:N]`, see [identifier_base62].
+  pub base62_hash: Option<&'a str>,
+  /// Replaces `[1]`, `[2]`, ..., see [ModulesConfig::local_ident_regexp].
+  pub local_ident_regexp: Option<&'a Regex>,
+  pub ident_start: IdentStart,
+}
+
+const BASE62_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+/// Make the digest `hash` able to start a css identifier, borrowing it when it already can.
+///
+/// A leading digit `0`-`9` is prefixed with `_`, or with [IdentStart::Letter] remapped to `g`-`p`,
+/// keeping the length. Those aren't hex digits, so the result of a hex digest can't collide with
+/// another hash, nor with a class that is intentionally prefixed with `_`. A leading `-` of a
+/// base64url digest is always prefixed with `_`, `-` followed by a digit doesn't start an
+/// identifier either.
+pub fn sanitize_ident_leading_char(hash: &str, ident_start: IdentStart) -> Cow<'_, str> {
+  match hash.as_bytes().first() {
+    Some(digit @ b'0'..=b'9') => match ident_start {
+      IdentStart::Underscore => Cow::Owned(format!("_{hash}")),
+      IdentStart::Letter => {
+        let letter = digit_to_letter(*digit);
+        let mut s = String::with_capacity(hash.len());
+        s.push(letter);
+        s.push_str(&hash[1..]);
+        Cow::Owned(s)
+      }
+    },
+    Some(b'-') => Cow::Owned(format!("_{hash}")),
+    _ => Cow::Borrowed(hash),
+  }
+}
+
+/// `0`-`9` to `g`-`p`, the letters following the hex digits.
+fn digit_to_letter(digit: u8) -> char {
+  (b'g' + (digit - b'0')) as char
+}
+
+/// Encode `value` with a base62 alphabet whose leading character is always a letter, so the
+/// result can start a css identifier as is.
+pub fn identifier_base62(mut value: u64) -> String {
+  let mut s = String::with_capacity(11);
+  s.push(BASE62_ALPHABET[(value % 52) as usize] as char);
+  value /= 52;
+  while value > 0 {
+    s.push(BASE62_ALPHABET[(value % 62) as usize] as char);
+    value /= 62;
+  }
+  s
+}
+
+/// What the hash in generated local idents is computed from.
+///
+/// `ResourcePath` is the default, moving a file changes every generated class name.
+/// `ResourceContent` keeps class names stable across directory refactors as long as the content
+/// of the file is unchanged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LocalIdentHashStrategy {
+  #[default]
+  ResourcePath,
+  ResourceContent,
+  ResourcePathAndContent,
+}
+
+impl LocalIdentHashStrategy {
+  pub fn path(&self) -> bool {
+    matches!(self, Self::ResourcePath | Self::ResourcePathAndContent)
+  }
+
+  pub fn content(&self) -> bool {
+    matches!(self, Self::ResourceContent | Self::ResourcePathAndContent)
+  }
+}
+
+/// The `hashStrategy` values and the strategy of each.
+const HASH_STRATEGIES: [(&str, LocalIdentHashStrategy); 3] = [
+  ("resource-path", LocalIdentHashStrategy::ResourcePath),
+  ("resource-content", LocalIdentHashStrategy::ResourceContent),
+  (
+    "resource-path-and-content",
+    LocalIdentHashStrategy::ResourcePathAndContent,
+  ),
+];
+
+impl FromStr for LocalIdentHashStrategy {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("hashStrategy", &HASH_STRATEGIES, s)
+  }
+}
+
+/// How the class names of a local are exported. `String` joins them with the composes separator,
+/// `Array` exports those of the locals composing others as an array instead, friendlier to
+/// `clsx`-like helpers, and `AlwaysArray` the ones of every local. Exported values stay strings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportClassNames {
+  #[default]
+  String,
+  Array,
+  AlwaysArray,
+}
+
+/// The `exportClassNames` values and how each exports class names.
+const EXPORT_CLASS_NAMES: [(&str, ExportClassNames); 3] = [
+  ("string", ExportClassNames::String),
+  ("array", ExportClassNames::Array),
+  ("always-array", ExportClassNames::AlwaysArray),
+];
+
+impl FromStr for ExportClassNames {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("exportClassNames", &EXPORT_CLASS_NAMES, s)
+  }
+}
+
+/// How the locals of a css module are exported. `CommonJs` assigns them to `module.exports`,
+/// `import styles from` and `import * as styles` both get them through the interop of the
+/// importer. `Default` makes them the default export of an ES module, the keys that are valid
+/// identifiers are named exports too. `Namespace` makes every key an export of its own, without a
+/// default export, so they're read with `import * as styles` or named imports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportsType {
+  #[default]
+  CommonJs,
+  Default,
+  Namespace,
+}
+
+impl ExportsType {
+  pub fn es_module(&self) -> bool {
+    !matches!(self, Self::CommonJs)
+  }
+}
+
+/// The `exportsType` values and how each exports locals.
+const EXPORTS_TYPES: [(&str, ExportsType); 3] = [
+  ("commonjs", ExportsType::CommonJs),
+  ("default", ExportsType::Default),
+  ("namespace", ExportsType::Namespace),
+];
+
+impl FromStr for ExportsType {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("exportsType", &EXPORTS_TYPES, s)
+  }
+}
+
+/// The line ending of generated code, `Crlf` for environments or fixtures normalized to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+  #[default]
+  Lf,
+  Crlf,
+}
+
+impl LineEnding {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Self::Lf => "\n",
+      Self::Crlf => "\r\n",
+    }
+  }
+}
+
+/// The `lineEnding` values and the line ending of each.
+const LINE_ENDINGS: [(&str, LineEnding); 2] = [("lf", LineEnding::Lf), ("crlf", LineEnding::Crlf)];
+
+impl FromStr for LineEnding {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("lineEnding", &LINE_ENDINGS, s)
+  }
+}
+
+/// How an ident or a hash that would start with a digit, or a hyphen and a digit, is made a valid
+/// css identifier. `Underscore` prefixes it with `_`, `Letter` maps the digit to a letter instead,
+/// `0`-`9` to `g`-`p`, so no ident starts with a `_` it wasn't given, e.g. for linters rejecting
+/// those class names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IdentStart {
+  #[default]
+  Underscore,
+  Letter,
+}
+
+/// The `identStart` values and how each starts idents.
+const IDENT_STARTS: [(&str, IdentStart); 2] = [
+  ("underscore", IdentStart::Underscore),
+  ("letter", IdentStart::Letter),
+];
+
+impl FromStr for IdentStart {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("identStart", &IDENT_STARTS, s)
+  }
+}
+
+/// What the css escape of an invalid code point becomes in the names of `:export`, i.e. zero, a
+/// surrogate or one out of range like `\110000`. `Replace` makes it U+FFFD like the css syntax
+/// spec, `Keep` keeps the escape as written, the white space ending it included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidEscape {
+  #[default]
+  Replace,
+  Keep,
+}
+
+/// The `invalidEscape` values and what each makes of an invalid escape.
+const INVALID_ESCAPES: [(&str, InvalidEscape); 2] = [
+  ("replace", InvalidEscape::Replace),
+  ("keep", InvalidEscape::Keep),
+];
+
+impl FromStr for InvalidEscape {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("invalidEscape", &INVALID_ESCAPES, s)
+  }
+}
+
+/// How the locals of a css module are kept apart from those of other files. `Class` renames them
+/// with `localIdentName`. `Attribute` keeps their names, and the selectors using them match a
+/// `[data-v-<hash>]` attribute too, like Vue or Svelte scoped styles. The hash is the one of the
+/// file, see [ModulesTransformConfig::scope_id], the elements styled need the same attribute.
+/// Keyframes keep their names unscoped then.
+///
+/// [ModulesTransformConfig::scope_id]: crate::ModulesTransformConfig::scope_id
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LocalScope {
+  #[default]
+  Class,
+  Attribute,
+}
+
+/// The `localScope` values and how each scopes locals.
+const LOCAL_SCOPES: [(&str, LocalScope); 2] = [
+  ("class", LocalScope::Class),
+  ("attribute", LocalScope::Attribute),
+];
+
+impl FromStr for LocalScope {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+    parse_option_value("localScope", &LOCAL_SCOPES, s)
+  }
 }
 
 bitflags! {
@@ -67,41 +576,160 @@ bitflags! {
   }
 }
 
-#[derive(Debug, Clone)]
-pub struct LocalsConvention(LocalsConventionFlags);
+type RenameLocal = fn(&str) -> String;
+
+/// How each convention renames a local and the name of its keys with
+/// [ModulesConfig::exports_by_convention], in the order their keys are emitted.
+const LOCALS_CONVENTION_ORDER: [(LocalsConventionFlags, &str, RenameLocal); 3] = [
+  (LocalsConventionFlags::ASIS, "asIs", |name| name.to_string()),
+  (LocalsConventionFlags::CAMELCASE, "camelCase", |name| {
+    name.to_lower_camel_case()
+  }),
+  (LocalsConventionFlags::DASHES, "dashes", |name| {
+    name.to_kebab_case()
+  }),
+];
+
+/// The name of the keys of a custom transform with [ModulesConfig::exports_by_convention].
+const CUSTOM_LOCALS_CONVENTION: &str = "custom";
+
+/// Map a local to the keys it's exported under, none leaves the local unexported.
+pub type LocalsConventionFn = Arc<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+#[derive(Derivative)]
+#[derivative(Debug, Clone)]
+pub struct LocalsConvention {
+  flags: LocalsConventionFlags,
+  #[derivative(Debug = "ignore")]
+  transform: Option<LocalsConventionFn>,
+}
+
+/// A custom transform can't be told apart from another, only that there's one.
+impl Hash for LocalsConvention {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.flags.hash(state);
+    self.transform.is_some().hash(state);
+  }
+}
 
 impl LocalsConvention {
+  /// A convention exporting each local under the keys `transform` returns only, e.g. snake_case.
+  pub fn custom(transform: LocalsConventionFn) -> Self {
+    Self {
+      flags: LocalsConventionFlags::empty(),
+      transform: Some(transform),
+    }
+  }
+
+  /// The keys `name` is exported under, always as-is first, then camelCase, then dashes, then the
+  /// ones of a custom transform. Keys may repeat, e.g. `foo` is the same in every convention.
+  pub fn keys<'a>(&'a self, name: &'a str) -> impl Iterator<Item = String> + 'a {
+    LOCALS_CONVENTION_ORDER
+      .iter()
+      .filter(|(flag, ..)| self.flags.contains(*flag))
+      .map(move |(.., rename)| rename(name))
+      .chain(
+        self
+          .transform
+          .iter()
+          .flat_map(move |transform| transform(name)),
+      )
+  }
+
+  /// Each convention of this one on its own, in the order their keys are emitted, with the name
+  /// their keys are grouped under by [ModulesConfig::exports_by_convention].
+  pub fn split(&self) -> Vec<(&'static str, LocalsConvention)> {
+    LOCALS_CONVENTION_ORDER
+      .iter()
+      .filter(|(flag, ..)| self.flags.contains(*flag))
+      .map(|(flag, name, _)| (*name, Self::from_flags(*flag)))
+      .chain(
+        self
+          .transform
+          .iter()
+          .map(|transform| (CUSTOM_LOCALS_CONVENTION, Self::custom(transform.clone()))),
+      )
+      .collect()
+  }
+
   pub fn as_is(&self) -> bool {
-    self.0.contains(LocalsConventionFlags::ASIS)
+    self.flags.contains(LocalsConventionFlags::ASIS)
   }
 
   pub fn camel_case(&self) -> bool {
-    self.0.contains(LocalsConventionFlags::CAMELCASE)
+    self.flags.contains(LocalsConventionFlags::CAMELCASE)
   }
 
   pub fn dashes(&self) -> bool {
-    self.0.contains(LocalsConventionFlags::DASHES)
+    self.flags.contains(LocalsConventionFlags::DASHES)
+  }
+
+  pub fn camel_case_only(&self) -> bool {
+    self.flags == LocalsConventionFlags::CAMELCASE && self.transform.is_none()
   }
+
+  pub fn dashes_only(&self) -> bool {
+    self.flags == LocalsConventionFlags::DASHES && self.transform.is_none()
+  }
+
+  fn from_flags(flags: LocalsConventionFlags) -> Self {
+    Self {
+      flags,
+      transform: None,
+    }
+  }
+}
+
+/// The value named `s` among the `values` of the css modules `option`. The error names `s` and the
+/// accepted names, for a typo in the config to be found.
+fn parse_option_value<T: Copy>(option: &str, values: &[(&str, T)], s: &str) -> anyhow::Result<T> {
+  let Some((_, value)) = values.iter().find(|(name, _)| *name == s) else {
+    bail!(
+      "css modules {option} \"{s}\" is invalid, expected one of {}",
+      values
+        .iter()
+        .map(|(name, _)| format!("\"{name}\""))
+        .collect::<Vec<_>>()
+        .join(", ")
+    );
+  };
+  Ok(*value)
 }
 
+/// The `localsConvention` values of css-loader and the keys each exports a local under.
+const LOCALS_CONVENTIONS: [(&str, LocalsConventionFlags); 5] = [
+  ("asIs", LocalsConventionFlags::ASIS),
+  (
+    "camelCase",
+    LocalsConventionFlags::ASIS.union(LocalsConventionFlags::CAMELCASE),
+  ),
+  ("camelCaseOnly", LocalsConventionFlags::CAMELCASE),
+  (
+    "dashes",
+    LocalsConventionFlags::ASIS.union(LocalsConventionFlags::DASHES),
+  ),
+  ("dashesOnly", LocalsConventionFlags::DASHES),
+];
+
 impl FromStr for LocalsConvention {
   type Err = anyhow::Error;
 
   fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-    Ok(match s {
-      "asIs" => Self(LocalsConventionFlags::ASIS),
-      "camelCase" => Self(LocalsConventionFlags::ASIS | LocalsConventionFlags::CAMELCASE),
-      "camelCaseOnly" => Self(LocalsConventionFlags::CAMELCASE),
-      "dashes" => Self(LocalsConventionFlags::ASIS | LocalsConventionFlags::DASHES),
-      "dashesOnly" => Self(LocalsConventionFlags::DASHES),
-      _ => bail!("css modules exportsLocalsConvention error"),
-    })
+    parse_option_value("localsConvention", &LOCALS_CONVENTIONS, s).map(Self::from_flags)
+  }
+}
+
+impl TryFrom<&str> for LocalsConvention {
+  type Error = anyhow::Error;
+
+  fn try_from(s: &str) -> std::result::Result<Self, Self::Error> {
+    s.parse()
   }
 }
 
 impl Default for LocalsConvention {
   fn default() -> Self {
-    Self(LocalsConventionFlags::ASIS)
+    Self::from_flags(LocalsConventionFlags::ASIS)
   }
 }
 
@@ -110,11 +738,44 @@ pub struct CssConfig {
   pub targets: Vec<String>,
   pub postcss: PostcssConfig,
   pub modules: ModulesConfig,
+  /// Emit asset urls relative to the css chunk rather than prefixed with `output.publicPath`,
+  /// so the same css works under any base path.
+  pub relative_urls: bool,
+  /// Also trim the Unicode white space and byte order mark around `url()`s, e.g. a no-break space
+  /// pasted along with the url. CSS only trims ASCII white space.
+  pub lenient_urls: bool,
+  /// Keep the percent escapes of `url()`s instead of decoding them, e.g. to request an asset named
+  /// `foo%20bar.png` as is.
+  pub keep_url_percent_escapes: bool,
+  /// Count how many times the hot paths of css modules run in each compilation, see
+  /// [TransformCounters], e.g. to profile a build with a lot of css. Nothing is counted otherwise.
+  pub debug_counters: bool,
+  /// Record the `[hash]` computed for each local of the css modules, see [CssPlugin::local_hashes],
+  /// e.g. to build subresource integrity metadata. Nothing is recorded otherwise.
+  pub record_hashes: bool,
 }
 
 impl CssPlugin {
   pub fn new(config: CssConfig) -> Self {
-    Self { config }
+    Self {
+      config,
+      hash_digest_length_warned: Default::default(),
+      local_idents: Default::default(),
+      local_hashes: Default::default(),
+      exports_cache: Default::default(),
+      counters: Default::default(),
+    }
+  }
+
+  /// The counts of the compilation last compiled with [CssConfig::debug_counters], all 0 without.
+  pub fn counters(&self) -> TransformCountersSnapshot {
+    self.counters.snapshot()
+  }
+
+  /// The `[hash]` of each local of the css module `module` as last parsed with
+  /// [CssConfig::record_hashes], `None` without or for other modules.
+  pub fn local_hashes(&self, module: &ModuleIdentifier) -> Option<IndexMap<JsWord, String>> {
+    self.local_hashes.hashes(module)
   }
 
   pub(crate) fn get_ordered_chunk_css_modules<'chunk_graph>(
@@ -306,3 +967,419 @@ fn compare_modules_by_identifier(a_id: &str, b_id: &str) -> cmp::Ordering {
     cmp::Ordering::Equal
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use rustc_hash::FxHashSet;
+
+  use super::*;
+
+  fn render(template: &str, filename: &str, local: &str) -> String {
+    LocalIdentName::from(template.to_string()).render(LocalIdentNameRenderOptions {
+      path_data: PathData::default().filename(filename).hash("abc"),
+      local: Some(local),
+      base62_hash: Some("aB9xyz"),
+      local_ident_regexp: None,
+      ident_start: IdentStart::Underscore,
+    })
+  }
+
+  #[test]
+  fn test_locals_convention_keys_order() {
+    let all = [
+      LocalsConventionFlags::ASIS,
+      LocalsConventionFlags::CAMELCASE,
+      LocalsConventionFlags::DASHES,
+    ];
+    // However the flags are combined, keys come out as-is, camelCase, then dashes.
+    for flags in [all, [all[2], all[1], all[0]], [all[1], all[2], all[0]]] {
+      let convention = LocalsConvention::from_flags(flags.into_iter().collect());
+      assert_eq!(
+        convention.keys("foo_bar-baz").collect::<Vec<_>>(),
+        vec!["foo_bar-baz", "fooBarBaz", "foo-bar-baz"]
+      );
+    }
+    let keys = |convention| {
+      LocalsConvention::from_str(convention)
+        .expect("valid")
+        .keys("foo_bar")
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(keys("asIs"), vec!["foo_bar"]);
+    assert_eq!(keys("camelCase"), vec!["foo_bar", "fooBar"]);
+    assert_eq!(keys("camelCaseOnly"), vec!["fooBar"]);
+    assert_eq!(keys("dashes"), vec!["foo_bar", "foo-bar"]);
+    assert_eq!(keys("dashesOnly"), vec!["foo-bar"]);
+  }
+
+  #[test]
+  fn test_parse_locals_convention() {
+    let flags = |convention: &LocalsConvention| {
+      (
+        convention.as_is(),
+        convention.camel_case(),
+        convention.dashes(),
+        convention.camel_case_only(),
+        convention.dashes_only(),
+      )
+    };
+    for (s, expected) in [
+      ("asIs", (true, false, false, false, false)),
+      ("camelCase", (true, true, false, false, false)),
+      ("camelCaseOnly", (false, true, false, true, false)),
+      ("dashes", (true, false, true, false, false)),
+      ("dashesOnly", (false, false, true, false, true)),
+    ] {
+      let convention = LocalsConvention::from_str(s).expect("valid");
+      assert_eq!(flags(&convention), expected, "{s}");
+      let convention = LocalsConvention::try_from(s).expect("valid");
+      assert_eq!(flags(&convention), expected, "{s}");
+    }
+    for s in ["camelcase", "", "asIs "] {
+      let error = LocalsConvention::try_from(s)
+        .expect_err("invalid")
+        .to_string();
+      assert_eq!(
+        error,
+        format!(
+          r#"css modules localsConvention "{s}" is invalid, expected one of "asIs", "camelCase", "camelCaseOnly", "dashes", "dashesOnly""#
+        )
+      );
+    }
+  }
+
+  #[test]
+  fn test_parse_option_values() {
+    assert_eq!(
+      LocalIdentHashStrategy::from_str("resource-content").expect("valid"),
+      LocalIdentHashStrategy::ResourceContent
+    );
+    assert_eq!(
+      LocalIdentHashStrategy::from_str("content")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules hashStrategy "content" is invalid, expected one of "resource-path", "resource-content", "resource-path-and-content""#
+    );
+    assert_eq!(
+      ExportClassNames::from_str("always-array").expect("valid"),
+      ExportClassNames::AlwaysArray
+    );
+    assert_eq!(
+      ExportClassNames::from_str("alwaysArray")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules exportClassNames "alwaysArray" is invalid, expected one of "string", "array", "always-array""#
+    );
+    assert_eq!(
+      LineEnding::from_str("crlf").expect("valid"),
+      LineEnding::Crlf
+    );
+    assert_eq!(
+      LineEnding::from_str("CRLF")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules lineEnding "CRLF" is invalid, expected one of "lf", "crlf""#
+    );
+    assert_eq!(
+      IdentStart::from_str("letter").expect("valid"),
+      IdentStart::Letter
+    );
+    assert_eq!(
+      IdentStart::from_str("letters")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules identStart "letters" is invalid, expected one of "underscore", "letter""#
+    );
+    assert_eq!(
+      LocalScope::from_str("attribute").expect("valid"),
+      LocalScope::Attribute
+    );
+    assert_eq!(
+      LocalScope::from_str("attr")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules localScope "attr" is invalid, expected one of "class", "attribute""#
+    );
+    assert_eq!(
+      InvalidEscape::from_str("keep").expect("valid"),
+      InvalidEscape::Keep
+    );
+    assert_eq!(
+      InvalidEscape::from_str("preserve")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules invalidEscape "preserve" is invalid, expected one of "replace", "keep""#
+    );
+    assert_eq!(
+      ExportsType::from_str("namespace").expect("valid"),
+      ExportsType::Namespace
+    );
+    assert_eq!(
+      ExportsType::from_str("esm")
+        .expect_err("invalid")
+        .to_string(),
+      r#"css modules exportsType "esm" is invalid, expected one of "commonjs", "default", "namespace""#
+    );
+  }
+
+  #[test]
+  fn test_parse_local_ident_name() {
+    for template in [
+      "[path][name][ext]__[local]",
+      "[folder]-[file]-[base]-[query][fragment]",
+      "[hash]",
+      "[hash:8]",
+      "[local]__[contenthash:6]",
+      "[fullhash:4]",
+      "[hash:base62]",
+      "[local]_[hash:base62:5]",
+      "[1]__[2]-[local]",
+      "[\\unknown\\]-[local]",
+      "no-tokens",
+    ] {
+      assert!(LocalIdentName::from_str(template).is_ok(), "{template}");
+    }
+    for (template, token) in [
+      ("[local]__[unknown]", "[unknown]"),
+      ("[hash:base64:5]", "[hash:base64:5]"),
+      ("[hash:base62:]", "[hash:base62:]"),
+      ("[Local]", "[Local]"),
+      ("[]", "[]"),
+    ] {
+      let err = LocalIdentName::from_str(template)
+        .expect_err(template)
+        .to_string();
+      assert!(
+        err.contains(&format!("unsupported token `{token}`")),
+        "{err}"
+      );
+      assert!(err.contains("[hash:base62:<length>]"), "{err}");
+    }
+  }
+
+  #[test]
+  fn test_render_escaped_tokens() {
+    assert_eq!(render("[\\local\\]-[local]", "a.css", "foo"), "[local]-foo");
+    assert_eq!(render("[\\name\\]_[name]", "a.css", "foo"), "[name]_a");
+  }
+
+  #[test]
+  fn test_render_local_ident_regexp() {
+    let render = |template: &str, regexp: &str, filename| {
+      LocalIdentName::from(template.to_string()).render(LocalIdentNameRenderOptions {
+        path_data: PathData::default().filename(filename).hash("abc"),
+        local: Some("foo"),
+        base62_hash: None,
+        local_ident_regexp: Some(&Regex::new(regexp).expect("valid")),
+        ident_start: IdentStart::Underscore,
+      })
+    };
+    let regexp = r"([^/]+)/(?:([^/.]+)\.)?styles\.module\.css$";
+    assert_eq!(
+      render("[1]__[local]", regexp, "src/Button/styles.module.css"),
+      "Button__foo"
+    );
+    assert_eq!(
+      render(
+        "[1]-[2]__[local]_[0]",
+        regexp,
+        "src/Button/dark.styles.module.css"
+      ),
+      "Button-dark__foo_Button-dark-styles-module-css"
+    );
+    // Groups that didn't match are empty, like all of them when the path doesn't match.
+    assert_eq!(
+      render(
+        "[1]-[2]-[3]__[local]",
+        regexp,
+        "src/Button/styles.module.css"
+      ),
+      "Button--__foo"
+    );
+    assert_eq!(
+      render("[1]__[local]", regexp, "src/Button/index.css"),
+      "__foo"
+    );
+    assert_eq!(
+      render("[\\1\\]__[local]", regexp, "src/Button/styles.module.css"),
+      "[1]__foo"
+    );
+  }
+
+  #[test]
+  fn test_folder_name() {
+    assert_eq!(folder_name("src/button/style.css"), "button");
+    assert_eq!(folder_name("src\\button\\style.css"), "button");
+    assert_eq!(folder_name("style.css"), "");
+  }
+
+  #[test]
+  fn test_index_folder_name() {
+    assert_eq!(
+      index_folder_name("src/button/index.module.css"),
+      Some("button")
+    );
+    assert_eq!(index_folder_name("src\\button\\index.css"), Some("button"));
+    assert_eq!(index_folder_name("index.css"), None);
+    assert_eq!(index_folder_name("src/button/reindex.css"), None);
+    assert_eq!(index_folder_name("src/index/style.css"), None);
+  }
+
+  #[test]
+  fn test_render_folder_and_name() {
+    assert_eq!(
+      render(
+        "[folder]__[name]__[local]",
+        "src/button/index.module.css",
+        "foo"
+      ),
+      "button__button__foo"
+    );
+    assert_eq!(
+      render("[folder]__[name]__[local]", "style.module.css", "foo"),
+      "__style-module__foo"
+    );
+    // Index files are named after their folder, unless they have none.
+    assert_eq!(
+      render("[name]__[local]", "src/button/index.css", "foo"),
+      "button__foo"
+    );
+    assert_eq!(render("[name]__[local]", "index.css", "foo"), "index__foo");
+    assert_eq!(
+      render("[\\name\\]_[name]", "src/button/index.css", "foo"),
+      "[name]_button"
+    );
+  }
+
+  #[test]
+  fn test_render_local() {
+    assert_eq!(render("[local]", "a.css", "foo-bar"), "foo-bar");
+    assert_eq!(render("[local]__[hash]", "a.css", "按钮"), "按钮__abc");
+    assert_eq!(render("[local]", "a.css", "foo bar"), "foo-bar");
+    assert_eq!(render("[local]", "a.css", "1foo"), "_1foo");
+    assert_eq!(render("[local]", "a.css", "-1foo"), "_-1foo");
+    assert_eq!(render("[local]", "a.css", "--foo"), "--foo");
+    assert_ne!(
+      render("[local]__[hash]", "a.css", "foo"),
+      render("[local]__[hash]", "a.css", "bar")
+    );
+  }
+
+  #[test]
+  fn test_render_base62_hash() {
+    assert_eq!(
+      render("[local]__[hash:base62]", "a.css", "foo"),
+      "foo__aB9xyz"
+    );
+    assert_eq!(render("[hash:base62:3]", "a.css", "foo"), "aB9");
+    assert_eq!(
+      render("[hash:base62:10]__[hash:2]", "a.css", "foo"),
+      "aB9xyz__ab"
+    );
+  }
+
+  #[test]
+  fn test_identifier_base62() {
+    for value in [0, 1, 51, 52, 61, 62, u64::MAX / 3, u64::MAX] {
+      let s = identifier_base62(value);
+      assert!(s.as_bytes()[0].is_ascii_alphabetic(), "{s}");
+      assert!(s.bytes().all(|b| b.is_ascii_alphanumeric()), "{s}");
+      assert!(!s.contains(['+', '/', '=']), "{s}");
+    }
+    assert_eq!(identifier_base62(0), "a");
+    assert_eq!(identifier_base62(52), "ab");
+    assert_ne!(identifier_base62(1), identifier_base62(52));
+  }
+
+  #[test]
+  fn test_sanitize_ident_leading_char() {
+    let hashes: Vec<_> = (0..10)
+      .map(|digit| {
+        sanitize_ident_leading_char(&format!("{digit}a1b2c3d"), IdentStart::Letter).into_owned()
+      })
+      .collect();
+    assert_eq!(
+      hashes,
+      [
+        "ga1b2c3d", "ha1b2c3d", "ia1b2c3d", "ja1b2c3d", "ka1b2c3d", "la1b2c3d", "ma1b2c3d",
+        "na1b2c3d", "oa1b2c3d", "pa1b2c3d"
+      ]
+    );
+    for hash in &hashes {
+      assert_eq!(hash.len(), 8);
+      assert_eq!(escape_local_ident(hash, IdentStart::Underscore), *hash);
+      // Collision-free with hashes that already start with a hex letter.
+      assert!(!hash.as_bytes()[0].is_ascii_hexdigit(), "{hash}");
+    }
+    for ident_start in [IdentStart::Underscore, IdentStart::Letter] {
+      let sanitize = |hash| sanitize_ident_leading_char(hash, ident_start);
+      assert!(matches!(sanitize("a1b2c3d4"), Cow::Borrowed("a1b2c3d4")));
+      assert_eq!(sanitize("f"), "f");
+      assert_eq!(sanitize("-1ab"), "_-1ab");
+      assert_eq!(sanitize("_ab"), "_ab");
+      assert_eq!(sanitize(""), "");
+    }
+    assert_eq!(sanitize_ident_leading_char("7", IdentStart::Letter), "n");
+    assert_eq!(
+      sanitize_ident_leading_char("7", IdentStart::Underscore),
+      "_7"
+    );
+    assert_eq!(
+      sanitize_ident_leading_char("6a1b2c3d", IdentStart::Underscore),
+      "_6a1b2c3d"
+    );
+  }
+
+  #[test]
+  fn test_sanitize_ident_leading_char_of_every_digest_start() {
+    const HEX: &str = "0123456789abcdef";
+    const BASE64: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    const BASE64_URL: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    for ident_start in [IdentStart::Underscore, IdentStart::Letter] {
+      for alphabet in [HEX, BASE64, BASE64_URL] {
+        let mut sanitized = FxHashSet::default();
+        for first in alphabet.chars() {
+          for second in alphabet.chars() {
+            let hash = format!("{first}{second}{}", &alphabet[..4]);
+            let result = sanitize_ident_leading_char(&hash, ident_start);
+            let leading = result.as_bytes()[0];
+            assert!(
+              !leading.is_ascii_digit() && leading != b'-',
+              "{hash} -> {result}"
+            );
+            // Only the leading character changes, or a `_` is prepended.
+            assert!(
+              result.ends_with(&hash[1..]) && result.len() - hash.len() <= 1,
+              "{hash} -> {result}"
+            );
+            assert_eq!(matches!(result, Cow::Borrowed(_)), result == hash);
+            sanitized.insert(result.into_owned());
+          }
+        }
+        if alphabet == HEX {
+          assert_eq!(sanitized.len(), HEX.len() * HEX.len());
+          if ident_start == IdentStart::Letter {
+            assert!(sanitized.iter().all(|hash| hash.len() == 6));
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_escape_local_ident() {
+    let escape = |s| escape_local_ident(s, IdentStart::Underscore);
+    assert_eq!(escape("my-pkg__foo"), "my-pkg__foo");
+    assert_eq!(escape("my.pkg/foo bar"), "my-pkg-foo-bar");
+    assert_eq!(escape("1foo"), "_1foo");
+    assert_eq!(escape("-1foo"), "_-1foo");
+    assert_eq!(escape("--1foo"), "--1foo");
+
+    let escape = |s| escape_local_ident(s, IdentStart::Letter);
+    assert_eq!(escape("my.pkg/foo bar"), "my-pkg-foo-bar");
+    assert_eq!(escape("1foo"), "hfoo");
+    assert_eq!(escape("-9foo"), "-pfoo");
+    assert_eq!(escape("--1foo"), "--1foo");
+    assert_eq!(escape("_1foo"), "_1foo");
+  }
+}
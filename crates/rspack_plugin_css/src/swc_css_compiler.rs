@@ -4,7 +4,7 @@ use once_cell::sync::Lazy;
 use rspack_core::rspack_sources::{self, SourceExt};
 use rspack_error::{internal_error, IntoTWithDiagnosticArray, Result, TWithDiagnosticArray};
 use swc_core::common::{
-  input::SourceFileInput, source_map::SourceMapGenConfig, FileName, SourceMap,
+  input::SourceFileInput, source_map::SourceMapGenConfig, BytePos, FileName, LineCol, SourceMap,
 };
 use swc_core::common::{Globals, GLOBALS};
 use swc_core::css::codegen::{
@@ -70,17 +70,27 @@ impl SwcCssCompiler {
     gen.emit(ast).map_err(|e| internal_error!(e.to_string()))?;
 
     if let Some(src_map_buf) = &mut src_map_buf {
-      let map = cm.build_source_map_with_config(src_map_buf, None, gen_source_map);
-      let mut raw_map = Vec::new();
-      map
-        .to_writer(&mut raw_map)
-        .map_err(|e| internal_error!(e.to_string()))?;
+      let raw_map = self.build_source_map(cm, src_map_buf, gen_source_map)?;
       Ok((output, Some(raw_map)))
     } else {
       Ok((output, None))
     }
   }
 
+  pub fn build_source_map(
+    &self,
+    cm: Arc<SourceMap>,
+    mappings: &mut [(BytePos, LineCol)],
+    gen_source_map: SwcCssSourceMapGenConfig,
+  ) -> Result<Vec<u8>> {
+    let map = cm.build_source_map_with_config(mappings, None, gen_source_map);
+    let mut raw_map = Vec::new();
+    map
+      .to_writer(&mut raw_map)
+      .map_err(|e| internal_error!(e.to_string()))?;
+    Ok(raw_map)
+  }
+
   pub fn minify(
     &self,
     filename: &str,
@@ -1,188 +1,5475 @@
-use std::{fmt::Write, hash::Hash, path::Path};
+use std::{
+  borrow::Cow,
+  cell::RefCell,
+  collections::BTreeMap,
+  fmt::{Display, Write},
+  hash::{Hash, Hasher},
+  path::{Component, Path},
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+  },
+};
 
-use heck::{ToKebabCase, ToLowerCamelCase};
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use once_cell::sync::Lazy;
-use regex::{Captures, Regex};
-use rspack_core::{Compilation, ModuleDependency, OutputOptions, PathData, RuntimeGlobals};
-use rspack_error::{internal_error, Result};
+use regex::Regex;
+use rspack_core::{
+  Compilation, DependencyType, MissingModule, Module, ModuleDependency, ModuleIdentifier,
+  ModuleType, NormalModule, OutputOptions, PathData, RuntimeGlobals,
+};
+use rspack_error::{internal_error, Diagnostic, DiagnosticKind, Result};
 use rspack_hash::{HashDigest, HashFunction, HashSalt, RspackHash};
+use rspack_identifier::IdentifierSet;
+use rustc_hash::{FxHashMap as HashMap, FxHasher};
+use swc_core::common::{BytePos, LineCol};
 use swc_core::css::modules::CssClassName;
 use swc_core::ecma::atoms::JsWord;
 
-use crate::plugin::{LocalIdentName, LocalIdentNameRenderOptions, LocalsConvention};
+use crate::dependency::CssImportDependency;
+use crate::plugin::{
+  escape_local_ident, identifier_base62, sanitize_ident_leading_char, ExportClassNames,
+  ExportsType, IdentStart, InvalidEscape, LocalIdentContext, LocalIdentFn, LocalIdentName,
+  LocalIdentNameRenderOptions, LocalScope, LocalsConvention, ModulesConfig,
+  FULL_HASH_DIGEST_LENGTH,
+};
 
 pub const AUTO_PUBLIC_PATH_PLACEHOLDER: &str = "__RSPACK_PLUGIN_CSS_AUTO_PUBLIC_PATH__";
-pub static AUTO_PUBLIC_PATH_PLACEHOLDER_REGEX: Lazy<Regex> =
-  Lazy::new(|| Regex::new(AUTO_PUBLIC_PATH_PLACEHOLDER).expect("Invalid regexp"));
+/// The key the content hash of a css module is exported under, see
+/// [ModulesConfig::export_content_hash].
+pub const CONTENT_HASH_EXPORT: &str = "__hash__";
+pub const CHUNK_RELATIVE_PATH_PLACEHOLDER: &str = "__RSPACK_PLUGIN_CSS_CHUNK_RELATIVE_PATH__";
+pub static PUBLIC_PATH_PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(&format!(
+    "{AUTO_PUBLIC_PATH_PLACEHOLDER}|{CHUNK_RELATIVE_PATH_PLACEHOLDER}"
+  ))
+  .expect("Invalid regexp")
+});
+/// Ends the names the css modules transform gives locals with [LocalScope::Attribute], a `NUL`
+/// can't be part of a parsed name, css parsers replace it.
+pub(crate) const SCOPED_LOCAL_MARKER: char = '\0';
+
+/// What [`replace_auto_public_path`] puts in place of [`AUTO_PUBLIC_PATH_PLACEHOLDER`], the
+/// `output.publicPath` of the compilation. [`CHUNK_RELATIVE_PATH_PLACEHOLDER`] is always replaced
+/// like `Runtime`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicPath {
+  /// Known at build time, e.g. a rendered `output.publicPath`.
+  Literal(String),
+  /// Only known at runtime, e.g. `__webpack_public_path__ = window.__CDN__` with
+  /// `output.publicPath: "auto"`. A stylesheet can't evaluate it, but it's loaded from there and
+  /// its urls are resolved against it, so they're made relative to the stylesheet instead.
+  Runtime,
+}
+
+impl PublicPath {
+  /// Always ends with a `/` unless empty, since the placeholder is directly followed by a filename.
+  fn render(&self, css_filename: &str) -> String {
+    match self {
+      Self::Literal(public_path) => {
+        rspack_core::PublicPath::ensure_ends_with_slash(public_path.to_string())
+      }
+      Self::Runtime => {
+        let depth = Path::new(css_filename)
+          .parent()
+          .into_iter()
+          .flat_map(Path::components)
+          .fold(0usize, |depth, component| match component {
+            Component::Normal(_) => depth + 1,
+            Component::ParentDir => depth.saturating_sub(1),
+            _ => depth,
+          });
+        "../".repeat(depth)
+      }
+    }
+  }
+}
+
+impl From<&rspack_core::PublicPath> for PublicPath {
+  fn from(public_path: &rspack_core::PublicPath) -> Self {
+    match public_path {
+      rspack_core::PublicPath::String(public_path) => Self::Literal(public_path.clone()),
+      rspack_core::PublicPath::Auto => Self::Runtime,
+    }
+  }
+}
+
+/// Replace every [`AUTO_PUBLIC_PATH_PLACEHOLDER`] of `css`, the content of the stylesheet emitted
+/// as `css_filename`, with `public_path`, and every [`CHUNK_RELATIVE_PATH_PLACEHOLDER`] with the
+/// path back to the output root, like the css plugin does for the stylesheets it emits.
+pub fn replace_auto_public_path<'a>(
+  css: &'a str,
+  public_path: &PublicPath,
+  css_filename: &str,
+) -> Cow<'a, str> {
+  let replacements = public_path_replacements(css, public_path, css_filename);
+  if replacements.is_empty() {
+    return Cow::Borrowed(css);
+  }
+  let mut result = String::with_capacity(css.len());
+  let mut last_end = 0;
+  for (start, end, path) in replacements {
+    result.push_str(&css[last_end..start]);
+    result.push_str(&path);
+    last_end = end;
+  }
+  result.push_str(&css[last_end..]);
+  Cow::Owned(result)
+}
+
+/// The start and end of every public path placeholder of `css`, see [replace_auto_public_path],
+/// and what replaces it. Replaced in place by the css plugin, which keeps the source map of the
+/// stylesheet.
+pub(crate) fn public_path_replacements(
+  css: &str,
+  public_path: &PublicPath,
+  css_filename: &str,
+) -> Vec<(usize, usize, String)> {
+  let public_path = public_path.render(css_filename);
+  let chunk_relative_path = PublicPath::Runtime.render(css_filename);
+  PUBLIC_PATH_PLACEHOLDER_REGEX
+    .find_iter(css)
+    .map(|mat| {
+      let path = if mat.as_str() == AUTO_PUBLIC_PATH_PLACEHOLDER {
+        &public_path
+      } else {
+        &chunk_relative_path
+      };
+      (mat.start(), mat.end(), path.to_string())
+    })
+    .collect()
+}
+
+/// The hash options of css modules local idents, those of the css plugin take precedence over
+/// `output`'s.
+pub struct LocalIdentHashOptions<'a> {
+  pub hash_function: &'a HashFunction,
+  pub hash_digest: &'a HashDigest,
+  pub hash_digest_length: usize,
+  pub hash_salt: &'a HashSalt,
+}
+
+impl<'a> LocalIdentHashOptions<'a> {
+  pub fn new(modules: &'a ModulesConfig, output: &'a OutputOptions) -> Self {
+    Self {
+      hash_function: modules
+        .hash_function
+        .as_ref()
+        .unwrap_or(&output.hash_function),
+      hash_digest: modules.hash_digest.as_ref().unwrap_or(&output.hash_digest),
+      hash_digest_length: modules
+        .hash_digest_length
+        .unwrap_or(output.hash_digest_length),
+      hash_salt: modules.hash_salt.as_ref().unwrap_or(&output.hash_salt),
+    }
+  }
+}
 
 pub struct ModulesTransformConfig<'a> {
-  filename: &'a Path,
+  /// With `/` separators on every platform.
+  filename: String,
   local_name_ident: &'a LocalIdentName,
-  hash_function: &'a HashFunction,
+  local_ident_regexp: Option<&'a Regex>,
+  local_ident_fn: Option<&'a LocalIdentFn>,
+  prefix: Option<&'a str>,
+  ident_start: IdentStart,
+  local_ident_max_length: Option<usize>,
+  local_scope: LocalScope,
+  hash_package_name: bool,
+  /// Hash state of the salt and resource shared by every local of the module, cloned per local.
+  hasher: RspackHash,
+  /// Like `hasher` but of the module content only, `None` unless `[contenthash]` is used.
+  content_hasher: Option<RspackHash>,
   hash_digest: &'a HashDigest,
   hash_digest_length: usize,
-  hash_salt: &'a HashSalt,
+  /// `[hash]` of every local the transform asked a name for, only recorded when set.
+  recorded_hashes: Option<&'a RefCell<IndexMap<JsWord, String>>>,
+  counters: Option<&'a TransformCounters>,
+  /// Only computed with `export_content_hash`.
+  content_hash: Option<String>,
 }
 
 impl<'a> ModulesTransformConfig<'a> {
   pub fn new(
     filename: &'a Path,
-    local_name_ident: &'a LocalIdentName,
+    content: &'a str,
+    modules: &'a ModulesConfig,
     output: &'a OutputOptions,
   ) -> Self {
+    Self::with_hash_options(
+      filename,
+      content,
+      modules,
+      LocalIdentHashOptions::new(modules, output),
+    )
+  }
+
+  fn with_hash_options(
+    filename: &'a Path,
+    content: &'a str,
+    modules: &'a ModulesConfig,
+    hash_options: LocalIdentHashOptions<'a>,
+  ) -> Self {
+    let mut hasher = RspackHash::with_salt(hash_options.hash_function, hash_options.hash_salt);
+    if let Some(hash_prefix) = &modules.hash_prefix {
+      hash_prefix.hash(&mut hasher);
+    }
+    let content_hasher = modules
+      .local_ident_name
+      .has_content_hash_placeholder()
+      .then(|| {
+        let mut content_hasher = hasher.clone();
+        content.hash(&mut content_hasher);
+        content_hasher
+      });
+    let filename = normalize_path_separators(&filename.to_string_lossy()).into_owned();
+    if modules.hash_strategy.path() {
+      Path::new(&filename).hash(&mut hasher);
+    }
+    if modules.hash_strategy.content() {
+      content.hash(&mut hasher);
+    }
+    let (hash_digest_length, _) = clamp_hash_digest_length(
+      hash_options.hash_function,
+      hash_options.hash_digest,
+      hash_options.hash_digest_length,
+    );
+    let content_hash = modules.export_content_hash.then(|| {
+      let mut content_hasher =
+        RspackHash::with_salt(hash_options.hash_function, hash_options.hash_salt);
+      content.hash(&mut content_hasher);
+      content_hasher
+        .digest(hash_options.hash_digest)
+        .rendered(hash_digest_length)
+        .to_string()
+    });
     Self {
       filename,
-      local_name_ident,
-      hash_function: &output.hash_function,
-      hash_digest: &output.hash_digest,
-      hash_digest_length: output.hash_digest_length,
-      hash_salt: &output.hash_salt,
+      local_name_ident: &modules.local_ident_name,
+      local_ident_regexp: modules.local_ident_regexp.as_ref(),
+      local_ident_fn: modules.local_ident_fn.as_ref(),
+      prefix: modules.prefix.as_deref(),
+      ident_start: modules.ident_start,
+      local_ident_max_length: modules.local_ident_max_length,
+      local_scope: modules.local_scope,
+      hash_package_name: modules.hash_package_name,
+      hasher,
+      content_hasher,
+      hash_digest: hash_options.hash_digest,
+      hash_digest_length,
+      recorded_hashes: None,
+      counters: None,
+      content_hash,
+    }
+  }
+
+  /// Record the `[hash]` computed for each local into `hashes`, e.g. to build integrity metadata
+  /// once the transform is done. The hash is recorded even when `localIdentName` doesn't use it.
+  pub fn record_hashes(mut self, hashes: &'a RefCell<IndexMap<JsWord, String>>) -> Self {
+    self.recorded_hashes = Some(hashes);
+    self
+  }
+
+  /// Count the locals renamed into `counters`, see [TransformCounters].
+  pub fn counters(mut self, counters: Option<&'a TransformCounters>) -> Self {
+    self.counters = counters;
+    self
+  }
+
+  /// Mix the name of the package the module belongs to into the hash of its locals, if
+  /// [ModulesConfig::hash_package_name] is set. `[contenthash]` stays the hash of the content.
+  pub fn package_name(mut self, package_name: Option<&str>) -> Self {
+    if self.hash_package_name
+      && let Some(package_name) = package_name
+    {
+      package_name.hash(&mut self.hasher);
+    }
+    self
+  }
+
+  /// The hash of the module content exported as [CONTENT_HASH_EXPORT] with `export_content_hash`,
+  /// with the hash options of the locals but neither `hash_prefix` nor the `hash_strategy`.
+  pub fn content_hash(&self) -> Option<&str> {
+    self.content_hash.as_deref()
+  }
+
+  /// The attribute the scoped selectors of the module match with [LocalScope::Attribute], e.g.
+  /// `data-v-1d2e3f4a`. Hashed like the locals but without one, so the same for the whole module
+  /// and on every build. Following `data-v-`, the hash can start with a digit.
+  pub fn scope_id(&self) -> Option<String> {
+    (self.local_scope == LocalScope::Attribute).then(|| {
+      let hash = self.hasher.clone().digest(self.hash_digest);
+      format!("data-v-{}", hash.rendered(self.hash_digest_length))
+    })
+  }
+}
+
+/// Clamp `hash_digest_length` between 1 and the length of the digests `hash_function` produces,
+/// returns the length css idents use and whether the requested one was out of those bounds,
+/// which is never the case of [FULL_HASH_DIGEST_LENGTH].
+///
+/// The css modules `hashDigestLength` can't be 0, but `output.hashDigestLength` it defaults to
+/// isn't checked, and every `[hash]` would be empty with it.
+pub fn clamp_hash_digest_length(
+  hash_function: &HashFunction,
+  hash_digest: &HashDigest,
+  hash_digest_length: usize,
+) -> (usize, bool) {
+  let max_length = RspackHash::new(hash_function)
+    .digest(hash_digest)
+    .encoded()
+    .len();
+  (
+    hash_digest_length.clamp(1, max_length),
+    hash_digest_length == 0
+      || hash_digest_length > max_length && hash_digest_length != FULL_HASH_DIGEST_LENGTH,
+  )
+}
+
+impl ModulesTransformConfig<'_> {
+  fn hash_params(&self) -> LocalIdentHashParams<'_> {
+    LocalIdentHashParams {
+      hasher: &self.hasher,
+      content_hasher: self.content_hasher.as_ref(),
+      hash_digest: self.hash_digest,
+      hash_digest_length: self.hash_digest_length,
     }
   }
 }
 
 impl swc_core::css::modules::TransformConfig for ModulesTransformConfig<'_> {
   fn new_name_for(&self, local: &JsWord) -> JsWord {
-    let hash = {
-      let mut hasher = RspackHash::with_salt(self.hash_function, self.hash_salt);
-      self.filename.hash(&mut hasher);
-      local.hash(&mut hasher);
-      let hash = hasher.digest(self.hash_digest);
-      let hash = hash.rendered(self.hash_digest_length);
-      if hash.as_bytes()[0].is_ascii_digit() {
-        format!("_{hash}")
+    if let Some(counters) = self.counters {
+      counters.count_new_name_for();
+    }
+    let filename = &self.filename;
+    let hashes = self.hash_params().hashes(local, self.ident_start);
+    if let Some(recorded_hashes) = self.recorded_hashes {
+      recorded_hashes
+        .borrow_mut()
+        .insert(local.clone(), hashes.hash.clone());
+    }
+    if self.local_scope == LocalScope::Attribute {
+      // Unchanged once the selectors are scoped, marked until then, see `scope_locals`.
+      return format!("{local}{SCOPED_LOCAL_MARKER}").into();
+    }
+    let render = |hashes: &LocalIdentHashes| {
+      let ident = if let Some(local_ident_fn) = self.local_ident_fn
+        && !local.is_empty()
+        && let Some(ident) = local_ident_fn(LocalIdentContext {
+          filename,
+          local,
+          hash: &hashes.hash,
+        })
+      {
+        escape_local_ident(&ident, self.ident_start)
       } else {
-        hash.into()
+        render_local_ident_with_hashes(
+          filename,
+          local,
+          self.local_name_ident,
+          self.local_ident_regexp,
+          hashes,
+          self.ident_start,
+        )
+      };
+      match self.prefix {
+        // Escaped again as a whole, a prefix like `-` may not start an identifier on its own.
+        Some(prefix) => escape_local_ident(&format!("{prefix}{ident}"), self.ident_start),
+        None => ident,
       }
     };
-    self
-      .local_name_ident
-      .render(LocalIdentNameRenderOptions {
-        path_data: PathData::default()
-          .filename(&self.filename.to_string_lossy())
-          .hash(&hash),
-        local: Some(local),
-      })
-      .into()
+    let ident = render(&hashes);
+    match self.local_ident_max_length {
+      Some(max_length) => {
+        truncate_local_ident(ident, || render(&hashes.masked()), &hashes.hash, max_length).into()
+      }
+      None => ident.into(),
+    }
+  }
+}
+
+/// Cut `ident` to `max_length` characters, see [ModulesConfig::local_ident_max_length].
+///
+/// The text after the hashes is cut first, then the text before them. The hashes are where `ident`
+/// and the ident `render_masked` renders with [masked](LocalIdentHashes::masked) hashes differ, so
+/// the same text in `[local]` or the path isn't mistaken for them. An ident without them, e.g.
+/// rendered with `[local]` only or by `local_ident_fn`, gets `_` and the `hash` instead of its end.
+fn truncate_local_ident(
+  ident: String,
+  render_masked: impl FnOnce() -> String,
+  hash: &str,
+  max_length: usize,
+) -> String {
+  if ident.chars().count() <= max_length {
+    return ident;
+  }
+  let masked = render_masked();
+  // From the start of the first hash to the end of the last one, e.g. both of `[hash]-[contenthash]`.
+  let span = (ident.len() == masked.len())
+    .then(|| {
+      let differs = |(a, b): (u8, u8)| a != b;
+      let start = ident.bytes().zip(masked.bytes()).position(differs)?;
+      let end = ident.len()
+        - ident
+          .bytes()
+          .rev()
+          .zip(masked.bytes().rev())
+          .position(differs)?;
+      Some((start, end))
+    })
+    .flatten();
+  let (head, hash, tail) = match span {
+    Some((start, end)) => (
+      &ident[..start],
+      ident[start..end].to_string(),
+      &ident[end..],
+    ),
+    None => (ident.as_str(), format!("_{hash}"), ""),
+  };
+  let budget = max_length.saturating_sub(hash.chars().count());
+  let head_length = head.chars().count().min(budget);
+  let mut truncated = head.chars().take(head_length).collect::<String>();
+  truncated.push_str(&hash);
+  truncated.extend(tail.chars().take(budget - head_length));
+  truncated
+}
+
+/// Hash state used to derive the `[hash]`, `[hash:base62]` and `[contenthash]` of a local ident.
+pub struct LocalIdentHashParams<'a> {
+  /// Already fed with everything shared by the locals of a module, e.g. salt and resource path.
+  pub hasher: &'a RspackHash,
+  /// Already fed with the salt and the module content, `[contenthash]` is left as is without it.
+  pub content_hasher: Option<&'a RspackHash>,
+  pub hash_digest: &'a HashDigest,
+  pub hash_digest_length: usize,
+}
+
+struct LocalIdentHashes {
+  hash: String,
+  base62_hash: String,
+  content_hash: Option<String>,
+}
+
+impl LocalIdentHashes {
+  /// Hashes of the same lengths whose every character differs from these, of letters only so
+  /// they're escaped alike, see [truncate_local_ident].
+  fn masked(&self) -> Self {
+    let mask = |hash: &str| {
+      hash
+        .chars()
+        .map(|c| if c == 'a' { 'b' } else { 'a' })
+        .collect::<String>()
+    };
+    Self {
+      hash: mask(&self.hash),
+      base62_hash: mask(&self.base62_hash),
+      content_hash: self.content_hash.as_deref().map(mask),
+    }
+  }
+}
+
+impl LocalIdentHashParams<'_> {
+  fn hashes(&self, local: &JsWord, ident_start: IdentStart) -> LocalIdentHashes {
+    let mut hasher = self.hasher.clone();
+    local.hash(&mut hasher);
+    let base62_hash = identifier_base62(hasher.finish());
+    let content_hash = self.content_hasher.map(|content_hasher| {
+      let mut content_hasher = content_hasher.clone();
+      local.hash(&mut content_hasher);
+      self.render_hash(content_hasher, ident_start)
+    });
+    LocalIdentHashes {
+      hash: self.render_hash(hasher, ident_start),
+      base62_hash,
+      content_hash,
+    }
+  }
+
+  fn render_hash(&self, hasher: RspackHash, ident_start: IdentStart) -> String {
+    let hash = hasher.digest(self.hash_digest);
+    sanitize_ident_leading_char(hash.rendered(self.hash_digest_length), ident_start).into_owned()
+  }
+}
+
+/// Render the css modules class name of `local` declared in `filename`.
+pub fn render_local_ident(
+  filename: &str,
+  local: &str,
+  local_ident_name: &LocalIdentName,
+  local_ident_regexp: Option<&Regex>,
+  hash_params: &LocalIdentHashParams,
+  ident_start: IdentStart,
+) -> String {
+  let hashes = hash_params.hashes(&local.into(), ident_start);
+  render_local_ident_with_hashes(
+    &normalize_path_separators(filename),
+    local,
+    local_ident_name,
+    local_ident_regexp,
+    &hashes,
+    ident_start,
+  )
+}
+
+/// Use `/` as the path separator, so idents are the same whatever platform built them.
+fn normalize_path_separators(path: &str) -> Cow<'_, str> {
+  if path.contains('\\') {
+    Cow::Owned(path.replace('\\', "/"))
+  } else {
+    Cow::Borrowed(path)
+  }
+}
+
+fn render_local_ident_with_hashes(
+  filename: &str,
+  local: &str,
+  local_ident_name: &LocalIdentName,
+  local_ident_regexp: Option<&Regex>,
+  hashes: &LocalIdentHashes,
+  ident_start: IdentStart,
+) -> String {
+  if local.is_empty() {
+    // Most templates would render nothing, or only a hash, there's no local to anchor on.
+    tracing::debug!("css modules got an empty local name in {filename}");
+    return match ident_start {
+      IdentStart::Underscore => format!("_{}", hashes.hash),
+      // The hash can already start an identifier.
+      IdentStart::Letter => hashes.hash.clone(),
+    };
+  }
+  local_ident_name.render(LocalIdentNameRenderOptions {
+    path_data: PathData::default()
+      .filename(filename)
+      .hash(&hashes.hash)
+      .content_hash_optional(hashes.content_hash.as_deref()),
+    local: Some(local),
+    base62_hash: Some(&hashes.base62_hash),
+    local_ident_regexp,
+    ident_start,
+  })
+}
+
+/// Identifies the exports of a css module by its resource, the hash of its content and the local
+/// idents rendered for it, see [ExportsCache]. The options the idents are rendered with are
+/// already in the idents, the others are those of the plugin owning the cache, which don't change.
+pub fn exports_cache_key(
+  resource: &str,
+  content: &str,
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+) -> u64 {
+  let mut hasher = FxHasher::default();
+  resource.hash(&mut hasher);
+  content.hash(&mut hasher);
+  for (key, names) in exports {
+    key.hash(&mut hasher);
+    for name in names {
+      match name {
+        CssClassName::Local { name } => (0u8, &name.value).hash(&mut hasher),
+        CssClassName::Global { name } => (1u8, &name.value).hash(&mut hasher),
+        CssClassName::Import { name, from } => (2u8, &name.value, from).hash(&mut hasher),
+      }
+    }
   }
+  hasher.finish()
 }
 
+/// Render the JS module of the css module `exports`, empty when there's nothing to export so the
+/// module can be left without any JS.
+///
+/// With a `cache` and the [exports_cache_key] of the module, the code is only rendered when the
+/// module or the modules it composes from changed. Not when source `mappings` are asked for, which point into the stylesheet just parsed.
 pub fn css_modules_exports_to_string(
   exports: &IndexMap<JsWord, Vec<CssClassName>>,
-  module: &dyn rspack_core::Module,
+  module: &dyn Module,
   compilation: &Compilation,
-  locals_convention: &LocalsConvention,
+  modules: &ModulesConfig,
+  runtime_requirements: &mut RuntimeGlobals,
+  mappings: Option<&mut Vec<(BytePos, LineCol)>>,
+  cache: Option<(&ExportsCache, u64)>,
 ) -> Result<String> {
-  let mut code = String::from("module.exports = {\n");
-  for (key, elements) in exports {
-    let content = elements
+  let render = |runtime_requirements: &mut RuntimeGlobals, mappings| {
+    let mut code = String::new();
+    write_css_modules_exports(
+      &mut code,
+      exports,
+      module,
+      compilation,
+      modules,
+      runtime_requirements,
+      mappings,
+    )?;
+    Ok(code)
+  };
+  match cache {
+    Some((cache, key)) if mappings.is_none() => {
+      let key = resolved_exports_cache_key(key, exports, module, compilation, modules);
+      cache.get_or_render(
+        module.identifier(),
+        key,
+        runtime_requirements,
+        |runtime_requirements| render(runtime_requirements, None),
+      )
+    }
+    _ => render(runtime_requirements, mappings),
+  }
+}
+
+/// Extend the `key` of the exports of `module` with what the code rendered depends on beyond the
+/// module: the ids of the modules it composes from, whether it's in a compose cycle and the mode.
+fn resolved_exports_cache_key(
+  key: u64,
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+  module: &dyn Module,
+  compilation: &Compilation,
+  modules: &ModulesConfig,
+) -> u64 {
+  let mut hasher = FxHasher::default();
+  key.hash(&mut hasher);
+  let resolve_module_id = composed_module_id_resolver(compilation, module, modules);
+  for name in exports.values().flatten() {
+    if let CssClassName::Import { from, .. } = name {
+      resolve_module_id(from).ok().hash(&mut hasher);
+    }
+  }
+  is_in_compose_cycle(compilation, module).hash(&mut hasher);
+  compilation.options.mode.is_development().hash(&mut hasher);
+  hasher.finish()
+}
+
+/// Like [`css_modules_exports_to_string`], but the code is written into `sink` line by line
+/// instead of being built in memory. A write failing errors with the part of the exports it was
+/// writing, e.g. the key of an export, `sink` is left with the lines before it.
+pub fn write_css_modules_exports(
+  sink: &mut impl Write,
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+  module: &dyn Module,
+  compilation: &Compilation,
+  modules: &ModulesConfig,
+  runtime_requirements: &mut RuntimeGlobals,
+  mappings: Option<&mut Vec<(BytePos, LineCol)>>,
+) -> Result<()> {
+  let resolve_module_id = composed_module_id_resolver(compilation, module, modules);
+  // Composing eagerly within a cycle reads the exports of a module that is still being evaluated.
+  let modules = if !modules.lazy_composes && is_in_compose_cycle(compilation, module) {
+    Cow::Owned(ModulesConfig {
+      lazy_composes: true,
+      ..modules.clone()
+    })
+  } else {
+    Cow::Borrowed(modules)
+  };
+  // Only in development, production bundles shouldn't leak the source paths.
+  let pathinfo = (modules.pathinfo && compilation.options.mode.is_development())
+    .then(|| module.readable_identifier(&compilation.options.context));
+  write_exports(
+    sink,
+    exports,
+    &modules,
+    pathinfo.as_deref(),
+    runtime_requirements,
+    mappings,
+    resolve_module_id,
+  )
+}
+
+/// The exports of a css module as emitted by [`css_modules_exports_to_string`], before they are
+/// rendered as JS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportEntry {
+  /// Under the `localsConvention`, keys that aren't emitted with `namedExport` are left out.
+  pub key: String,
+  /// The class of the key itself first, then the ones it composes in declaration order, local or
+  /// imported alike, like css-loader. Only the order of the rules in the css decides which of them
+  /// wins, not the order of the class names.
+  pub values: Vec<ExportValue>,
+  /// The selector the first local class of the key is declared in, `None` for `:export` values,
+  /// keys only composing classes of other modules, or when the selectors weren't collected.
+  pub source_selector: Option<String>,
+  /// Also emitted as a named export of an ES css module, e.g. to generate the
+  /// `export const key: string` of a `.d.ts`. Keys that aren't valid identifiers never are.
+  pub named_export: bool,
+  /// The locals convention whose object the key is in with
+  /// [ModulesConfig::exports_by_convention], e.g. `camelCase`.
+  pub convention: Option<&'static str>,
+  origin: Option<BytePos>,
+  is_array: bool,
+}
+
+impl ExportEntry {
+  /// Whether the class names are exported as an array rather than a string, see
+  /// [ModulesConfig::export_class_names].
+  pub fn is_array(&self) -> bool {
+    self.is_array
+  }
+}
+
+/// A class name bound to an [`ExportEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportValue {
+  Static(String),
+  /// A js expression evaluating to the class names exported by another css module. Of an ES css
+  /// module, it reads the namespace of the module imported at the top of its code, see
+  /// [imported_module_binding].
+  Composed(String),
+}
+
+/// The entries [`css_modules_exports_to_string`] renders, in the same order.
+///
+/// `selectors` are collected by [`collect_selectors`](crate::visitors::collect_selectors) from
+/// the stylesheet before it's compiled, the source selector of each entry is looked up there.
+pub fn css_modules_export_entries(
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+  module: &dyn Module,
+  compilation: &Compilation,
+  modules: &ModulesConfig,
+  selectors: Option<&HashMap<BytePos, String>>,
+) -> Result<Vec<ExportEntry>> {
+  export_entries(
+    exports,
+    modules,
+    selectors,
+    &mut IndexSet::default(),
+    composed_module_id_resolver(compilation, module, modules),
+  )
+}
+
+/// Resolve the id of the module composed from the request `from` of `module`.
+fn composed_module_id_resolver<'a>(
+  compilation: &'a Compilation,
+  module: &'a dyn Module,
+  modules: &'a ModulesConfig,
+) -> impl Fn(&str) -> Result<String> + 'a {
+  let import_modules = composed_modules_by_from(compilation, module);
+  move |from: &str| {
+    import_modules
+      .get(from)
+      // Required as is the missing module throws, failing the whole css module.
+      .filter(|from| !modules.resilient || !is_missing_module(**from))
+      .and_then(|from| {
+        compilation
+          .chunk_graph
+          .get_module_id(from.identifier())
+          .clone()
+      })
+      .ok_or_else(|| {
+        internal_error!(
+          "Failed to resolve `{from}` composed by css module {}",
+          module.identifier()
+        )
+      })
+  }
+}
+
+/// The ids of the modules composed from are collected into `imports`, in the order the bindings
+/// importing them are numbered in.
+fn export_entries(
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+  modules: &ModulesConfig,
+  selectors: Option<&HashMap<BytePos, String>>,
+  imports: &mut IndexSet<String>,
+  resolve_module_id: impl Fn(&str) -> Result<String>,
+) -> Result<Vec<ExportEntry>> {
+  // Last and as is whatever the locals convention, the key is reserved.
+  let content_hash = exports
+    .get(&JsWord::from(CONTENT_HASH_EXPORT))
+    .filter(|_| modules.export_content_hash);
+  let conventions = if modules.exports_by_convention {
+    modules
+      .locals_convention
+      .split()
+      .into_iter()
+      .map(|(name, convention)| (Some(name), convention))
+      .collect()
+  } else {
+    vec![(None, modules.locals_convention.clone())]
+  };
+  let mut keys = vec![];
+  for (name, convention) in conventions {
+    let mut grouped = group_exports_by_convention(
+      exports
+        .iter()
+        .filter(|(key, _)| content_hash.is_none() || *key != CONTENT_HASH_EXPORT),
+      &convention,
+    );
+    if modules.sort_exports {
+      grouped.sort_keys();
+    }
+    keys.extend(
+      grouped
+        .into_iter()
+        .map(|(key, elements)| (name, key, elements)),
+    );
+  }
+  if let Some(content_hash) = content_hash {
+    keys.push((
+      None,
+      CONTENT_HASH_EXPORT.to_string(),
+      content_hash.iter().collect(),
+    ));
+  }
+  let mut entries = vec![];
+  for (convention, key, elements) in keys {
+    let values = elements
       .iter()
-      .map(|element| match element {
+      .filter_map(|element| match element {
+        // `composes: a from global;` is a global class, there's no module to require it from.
         CssClassName::Local { name } | CssClassName::Global { name } => {
-          serde_json::to_string(&name.value).expect("TODO:")
-        }
-        CssClassName::Import { name, from } => {
-          let name = serde_json::to_string(&name.value).expect("TODO:");
-
-          let from = compilation
-            .module_graph
-            .module_graph_module_by_identifier(&module.identifier())
-            .and_then(|mgm| {
-              // workaround
-              mgm.dependencies.iter().find_map(|id| {
-                let dependency = compilation.module_graph.dependency_by_id(id);
-                if let Some(dependency) = dependency {
-                  if dependency.request() == from {
-                    return compilation
-                      .module_graph
-                      .module_graph_module_by_dependency_id(id);
-                  }
-                }
-                None
-              })
-            })
-            .expect("should have css from module");
-
-          let from = serde_json::to_string(from.id(&compilation.chunk_graph)).expect("TODO:");
-          format!("{}({from})[{name}]", RuntimeGlobals::REQUIRE)
+          Some(Ok(ExportValue::Static(name.value.to_string())))
         }
+        CssClassName::Import { name, from } => match resolve_module_id(from) {
+          Ok(from) => {
+            let (index, _) = imports.insert_full(from);
+            Some(
+              composed_class_names(&name.value, &imports[index], index, modules)
+                .map(ExportValue::Composed),
+            )
+          }
+          // Left out until the module can be resolved, see `missing_composed_module_warnings`.
+          Err(_) if modules.resilient => None,
+          Err(e) => Some(Err(e)),
+        },
       })
-      .collect::<Vec<_>>()
-      .join(" + \" \" + ");
-    if locals_convention.as_is() {
-      writeln!(
-        code,
-        "  {}: {},",
-        serde_json::to_string(&key).expect("TODO:"),
-        content,
+      .collect::<Result<Vec<_>>>()?;
+    if modules.named_export && !is_named_export(&key) {
+      continue;
+    }
+    let is_array = match modules.export_class_names {
+      ExportClassNames::String => false,
+      ExportClassNames::Array => values.len() > 1,
+      ExportClassNames::AlwaysArray => {
+        values.len() > 1
+          || elements
+            .iter()
+            .any(|element| matches!(element, CssClassName::Local { .. }))
+      }
+    };
+    // The selector of the first local class of the key, composed classes come from elsewhere.
+    let origin = elements.iter().find_map(|element| match element {
+      CssClassName::Local { name } if !name.span.is_dummy() => Some(name.span.lo),
+      _ => None,
+    });
+    entries.push(ExportEntry {
+      source_selector: origin
+        .zip(selectors)
+        .and_then(|(origin, selectors)| selectors.get(&origin).cloned()),
+      named_export: modules.exports_type.es_module()
+        && convention.is_none()
+        && is_named_export(&key),
+      convention,
+      key,
+      values,
+      origin,
+      is_array,
+    });
+  }
+  Ok(entries)
+}
+
+fn write_exports(
+  sink: &mut impl Write,
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+  modules: &ModulesConfig,
+  pathinfo: Option<&str>,
+  runtime_requirements: &mut RuntimeGlobals,
+  mappings: Option<&mut Vec<(BytePos, LineCol)>>,
+  resolve_module_id: impl Fn(&str) -> Result<String>,
+) -> Result<()> {
+  if exports.is_empty() {
+    return Ok(());
+  }
+  let mut imports = IndexSet::default();
+  let entries = export_entries(exports, modules, None, &mut imports, resolve_module_id)?;
+  let eol = modules.line_ending.as_str();
+  let namespace = modules.exports_type == ExportsType::Namespace;
+  let (open, close) = if modules.freeze_exports && !namespace {
+    ("Object.freeze({", "})")
+  } else {
+    ("{", "}")
+  };
+  if let Some(pathinfo) = pathinfo {
+    write!(sink, "// CSS module: {pathinfo}{eol}").map_err(write_error("the pathinfo comment"))?;
+  }
+  if modules.exports_type.es_module() {
+    write!(
+      sink,
+      "{}(exports);{eol}",
+      RuntimeGlobals::MAKE_NAMESPACE_OBJECT
+    )
+    .map_err(write_error("the start of the exports"))?;
+    // Imported once, their exports are read off their namespaces, like the harmony imports of js.
+    for (index, from) in imports.iter().enumerate() {
+      write!(
+        sink,
+        "var {} = {}({});{eol}",
+        imported_module_binding(index),
+        RuntimeGlobals::REQUIRE,
+        to_js_string(from, modules.single_quote)?
       )
-      .map_err(|e| internal_error!(e.to_string()))?;
+      .map_err(write_error(format_args!("the import of {from}")))?;
+    }
+  }
+  match modules.exports_type {
+    ExportsType::CommonJs => write!(sink, "module.exports = {open}{eol}"),
+    ExportsType::Namespace => write!(
+      sink,
+      "{}(exports, {{{eol}",
+      RuntimeGlobals::DEFINE_PROPERTY_GETTERS,
+    ),
+    ExportsType::Default => write!(sink, "var __WEBPACK_DEFAULT_EXPORT__ = {open}{eol}"),
+  }
+  .map_err(write_error("the start of the exports"))?;
+
+  let mut named_keys = vec![];
+  // Every export property is emitted on its own line, after the lines opening the object.
+  let mut line = usize::from(pathinfo.is_some())
+    + if modules.exports_type.es_module() {
+      2 + imports.len()
+    } else {
+      1
+    };
+  let mut lines = Vec::with_capacity(entries.len());
+  let mut convention = None;
+  for (index, entry) in entries.iter().enumerate() {
+    if entry.convention != convention {
+      if let Some(convention) = convention {
+        write!(sink, "  }},{eol}").map_err(write_error(format_args!(
+          "the end of the {convention} exports"
+        )))?;
+        line += 1;
+      }
+      convention = entry.convention;
+      if let Some(convention) = convention {
+        let key = to_js_string(convention, modules.single_quote)?;
+        write!(sink, "  {key}: {{{eol}").map_err(write_error(format_args!(
+          "the start of the {convention} exports"
+        )))?;
+        line += 1;
+        if modules.exports_type == ExportsType::Default {
+          named_keys.push(key);
+        }
+      }
     }
-    if locals_convention.camel_case() {
-      writeln!(
-        code,
-        "  {}: {},",
-        serde_json::to_string(&key.to_lower_camel_case()).expect("TODO:"),
-        content,
+    let indent = if convention.is_some() { "    " } else { "  " };
+    let is_composed = entry
+      .values
+      .iter()
+      .any(|value| matches!(value, ExportValue::Composed(_)));
+    let content = if entry.is_array {
+      class_names_array(&entry.values, modules.single_quote)?
+    } else {
+      join_class_names(
+        &entry.values,
+        &modules.composes_separator,
+        modules.single_quote,
+      )?
+    };
+    let key = to_js_string(&entry.key, modules.single_quote)?;
+    if namespace {
+      write!(
+        sink,
+        "{indent}{key}: function () {{ return {content}; }},{eol}"
       )
-      .map_err(|e| internal_error!(e.to_string()))?;
+    } else if modules.lazy_composes && is_composed {
+      // Enumerable like any other property of the object literal.
+      write!(sink, "{indent}get {key}() {{ return {content}; }},{eol}")
+    } else {
+      write!(sink, "{indent}{key}: {content},{eol}")
+    }
+    .map_err(write_error(format_args!(
+      "the export {key} ({} of {})",
+      index + 1,
+      entries.len()
+    )))?;
+    if entry.named_export {
+      named_keys.push(key);
     }
-    if locals_convention.dashes() {
-      writeln!(
-        code,
-        "  {}: {},",
-        serde_json::to_string(&key.to_kebab_case()).expect("TODO:"),
-        content,
+    lines.push(LineCol {
+      line: line as u32,
+      col: indent.len() as u32,
+    });
+    line += 1;
+  }
+  if let Some(convention) = convention {
+    write!(sink, "  }},{eol}").map_err(write_error(format_args!(
+      "the end of the {convention} exports"
+    )))?;
+  }
+
+  if let Some(mappings) = mappings {
+    mappings.extend(
+      entries
+        .iter()
+        .zip(lines)
+        .filter_map(|(entry, line_col)| entry.origin.map(|origin| (origin, line_col))),
+    );
+  }
+
+  let last_eol = if modules.trailing_newline { eol } else { "" };
+  if !modules.exports_type.es_module() {
+    return write!(sink, "{close};{last_eol}").map_err(write_error("the end of the exports"));
+  }
+
+  runtime_requirements.insert(RuntimeGlobals::MAKE_NAMESPACE_OBJECT);
+  runtime_requirements.insert(RuntimeGlobals::DEFINE_PROPERTY_GETTERS);
+  if namespace {
+    return write!(sink, "}});{last_eol}").map_err(write_error("the end of the exports"));
+  }
+  write!(
+    sink,
+    "{close};{eol}{}(exports, {{{eol}",
+    RuntimeGlobals::DEFINE_PROPERTY_GETTERS
+  )
+  .map_err(write_error("the end of the default export"))?;
+  for key in named_keys {
+    write!(
+      sink,
+      "  {key}: function () {{ return __WEBPACK_DEFAULT_EXPORT__[{key}]; }},{eol}"
+    )
+    .map_err(write_error(format_args!("the named export {key}")))?;
+  }
+  write!(
+    sink,
+    "  {}: function () {{ return __WEBPACK_DEFAULT_EXPORT__; }},{eol}}});{last_eol}",
+    to_js_string("default", modules.single_quote)?
+  )
+  .map_err(write_error("the end of the named exports"))
+}
+
+/// The error of a write of `part` of the exports into the sink, e.g. a sink streaming elsewhere
+/// failing halfway, so the code written until then is known to be cut there.
+fn write_error(part: impl Display) -> impl FnOnce(std::fmt::Error) -> rspack_error::Error {
+  move |e| internal_error!("Failed to write {part} of the css module exports: {e}")
+}
+
+/// The binding the `index`th module composed from by an ES css module is imported into.
+fn imported_module_binding(index: usize) -> String {
+  format!("__WEBPACK_IMPORTED_MODULE_{index}__")
+}
+
+/// The js expression of the class names exported as `name` by the css module of id `from`, bound
+/// to [imported_module_binding] of `index` in an ES css module.
+///
+/// A commonjs css module requires it where the class names are read. An ES one reads them off the
+/// namespace it imported the module into, the exports type only changes the export read.
+/// A `composes` is a sync dependency, the module is in a chunk loaded with the composing one even
+/// when imported with `import()` elsewhere, so it's always there to be required.
+fn composed_class_names(
+  name: &str,
+  from: &str,
+  index: usize,
+  modules: &ModulesConfig,
+) -> Result<String> {
+  let name = to_js_string(
+    &imported_export_key(name, &modules.locals_convention),
+    modules.single_quote,
+  )?;
+  // The imported key is of the first convention, in an object of its own with
+  // `exports_by_convention`.
+  let key = match modules.locals_convention.split().first() {
+    Some((convention, _)) if modules.exports_by_convention => {
+      format!(
+        "[{}][{name}]",
+        to_js_string(convention, modules.single_quote)?
       )
-      .map_err(|e| internal_error!(e.to_string()))?;
     }
+    _ => format!("[{name}]"),
+  };
+  Ok(match modules.exports_type {
+    ExportsType::CommonJs => format!(
+      "{}({}){key}",
+      RuntimeGlobals::REQUIRE,
+      to_js_string(from, modules.single_quote)?
+    ),
+    // Every key of an ES css module is reachable through its default export,
+    // named exports only cover valid identifiers.
+    ExportsType::Default => format!(
+      "{}[{}]{key}",
+      imported_module_binding(index),
+      to_js_string("default", modules.single_quote)?
+    ),
+    ExportsType::Namespace => format!("{}{key}", imported_module_binding(index)),
+  })
+}
+
+/// The class names bound to an export key as a js array expression.
+///
+/// Composed class names may themselves be arrays, `concat` flattens them, or `undefined` for a
+/// missing class, which is filtered out like by [join_class_names].
+fn class_names_array(values: &[ExportValue], single_quote: bool) -> Result<String> {
+  let is_composed = values
+    .iter()
+    .any(|value| matches!(value, ExportValue::Composed(_)));
+  let values = values
+    .iter()
+    .map(|value| match value {
+      ExportValue::Static(name) => to_js_string(name, single_quote),
+      ExportValue::Composed(expr) => Ok(expr.clone()),
+    })
+    .collect::<Result<Vec<_>>>()?
+    .join(", ");
+  if is_composed {
+    return Ok(format!("[].concat({values}).filter(Boolean)"));
   }
-  code += "};\n";
-  Ok(code)
+  Ok(format!("[{values}]"))
 }
 
-static STRING_MULTILINE: Lazy<Regex> =
-  Lazy::new(|| Regex::new(r"\\[\n\r\f]").expect("Invalid RegExp"));
+/// Map the request of each dependency of `module` to the module it resolved to, the first
+/// dependency of a request wins. A request rewritten by a `beforeResolve` hook, e.g. to an alias,
+/// is mapped under the request as written too.
+pub fn import_modules_by_request<'a>(
+  compilation: &'a Compilation,
+  module: &dyn Module,
+) -> HashMap<&'a str, &'a dyn Module> {
+  modules_by_request(compilation, module, |_| true, true)
+}
 
-static TRIM_WHITE_SPACES: Lazy<Regex> =
-  Lazy::new(|| Regex::new(r"(^[ \t\n\r\f]*|[ \t\n\r\f]*$)").expect("Invalid RegExp"));
+/// Like [import_modules_by_request], but only of the dependencies `composes` added, so an
+/// `@import` or `url()` of the same request can't be picked instead.
+fn composed_modules_by_request<'a>(
+  compilation: &'a Compilation,
+  module: &dyn Module,
+) -> HashMap<&'a str, &'a dyn Module> {
+  modules_by_request(compilation, module, is_compose_dependency, false)
+}
 
-static UNESCAPE: Lazy<Regex> =
-  Lazy::new(|| Regex::new(r"\\([0-9a-fA-F]{1,6}[ \t\n\r\f]?|[\s\S])").expect("Invalid RegExp"));
+/// Like [composed_modules_by_request], but also keyed by the `from` of the `composes` as written,
+/// which isn't the request resolved when a `beforeResolve` hook rewrote it, e.g. to an alias.
+fn composed_modules_by_from<'a>(
+  compilation: &'a Compilation,
+  module: &dyn Module,
+) -> HashMap<&'a str, &'a dyn Module> {
+  modules_by_request(compilation, module, is_compose_dependency, true)
+}
 
-static DATA: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?i)data:").expect("Invalid RegExp"));
+fn is_compose_dependency(dependency: &dyn ModuleDependency) -> bool {
+  dependency.dependency_type() == &DependencyType::CssCompose
+}
 
-pub fn normalize_url(s: &str) -> String {
-  let result = STRING_MULTILINE.replace_all(s, "");
-  let result = TRIM_WHITE_SPACES.replace_all(&result, "");
-  let result = UNESCAPE.replace_all(&result, |caps: &Captures| {
-    caps
-      .get(0)
-      .and_then(|m| {
-        let m = m.as_str();
-        if m.len() > 2 {
-          if let Ok(r_u32) = u32::from_str_radix(m[1..].trim(), 16) {
-            if let Some(ch) = char::from_u32(r_u32) {
-              return Some(format!("{}", ch));
-            }
-          }
-          None
-        } else {
-          Some(m[1..2].to_string())
-        }
+/// Errors for the `composes: a from "./a.js"` of css modules resolving to modules other than css
+/// modules, whose exports don't hold class names.
+pub(crate) fn composes_from_non_css_module_errors(compilation: &Compilation) -> Vec<Diagnostic> {
+  let mut errors = vec![];
+  for module in compilation.module_graph.modules().values() {
+    if !is_css_module(module.as_ref()) {
+      continue;
+    }
+    for (request, from) in composed_modules_by_request(compilation, module.as_ref()) {
+      // A request that failed to resolve is already reported as such.
+      if is_css_module(from) || is_missing_module(from) {
+        continue;
+      }
+      errors.push(Diagnostic::error(
+        "CSS Modules".to_string(),
+        format!(
+          "file: {} `composes` from \"{request}\" resolves to a {} module, classes can only be composed from css modules (css/module).",
+          module.readable_identifier(&compilation.options.context),
+          from.module_type()
+        ),
+        0,
+        0,
+      ));
+    }
+  }
+  errors
+}
+
+/// Warnings for the `composes: a from "./a.css"` of css modules that failed to resolve, with
+/// `resilient` the classes composed from them are left out instead of throwing at runtime.
+pub(crate) fn missing_composed_module_warnings(compilation: &Compilation) -> Vec<Diagnostic> {
+  let mut warnings = vec![];
+  for module in compilation.module_graph.modules().values() {
+    if !is_css_module(module.as_ref()) {
+      continue;
+    }
+    for (request, from) in composed_modules_by_request(compilation, module.as_ref()) {
+      if !is_missing_module(from) {
+        continue;
+      }
+      warnings.push(Diagnostic::warn(
+        "CSS Modules".to_string(),
+        format!(
+          "file: {} `composes` from \"{request}\" can't be resolved, the classes composed from it are left out until it can.",
+          module.readable_identifier(&compilation.options.context),
+        ),
+        0,
+        0,
+      ));
+    }
+  }
+  warnings
+}
+
+/// The idents generated for the locals of the css modules of a compiler, shared by its parsers to
+/// tell when different locals get the same ident, e.g. because `hashDigestLength` is too short to
+/// tell their hashes apart.
+#[derive(Debug, Default)]
+pub struct LocalIdentCollector {
+  /// The `(local, ident)` pairs of each module, replaced when the module is parsed again.
+  idents: Mutex<HashMap<ModuleIdentifier, Vec<(JsWord, JsWord)>>>,
+}
+
+impl LocalIdentCollector {
+  pub fn record(&self, module: ModuleIdentifier, idents: Vec<(JsWord, JsWord)>) {
+    self
+      .idents
+      .lock()
+      .expect("should lock the local idents")
+      .insert(module, idents);
+  }
+
+  /// Forget the idents of the modules `is_live` rejects, e.g. deleted or renamed since the last
+  /// compilation, so they don't pile up over the rebuilds of a watch session.
+  pub fn retain(&self, is_live: impl Fn(&ModuleIdentifier) -> bool) {
+    self
+      .idents
+      .lock()
+      .expect("should lock the local idents")
+      .retain(|module, _| is_live(module));
+  }
+
+  /// Every ident of more than one local with its `(module, local)` pairs, sorted to report them in
+  /// a stable order. Modules `is_live` rejects, e.g. removed from the compilation, are skipped.
+  pub fn collisions(
+    &self,
+    is_live: impl Fn(&ModuleIdentifier) -> bool,
+  ) -> Vec<(JsWord, Vec<(ModuleIdentifier, JsWord)>)> {
+    let idents = self.idents.lock().expect("should lock the local idents");
+    let mut locals_by_ident: HashMap<&JsWord, Vec<(ModuleIdentifier, JsWord)>> = HashMap::default();
+    for (module, idents) in idents.iter().filter(|(module, _)| is_live(module)) {
+      for (local, ident) in idents {
+        locals_by_ident
+          .entry(ident)
+          .or_default()
+          .push((*module, local.clone()));
+      }
+    }
+    let mut collisions = locals_by_ident
+      .into_iter()
+      .filter(|(_, locals)| locals.len() > 1)
+      .map(|(ident, mut locals)| {
+        locals.sort_by(|(a, a_local), (b, b_local)| {
+          (a.as_str(), &**a_local).cmp(&(b.as_str(), &**b_local))
+        });
+        (ident.clone(), locals)
       })
-      .unwrap_or(caps[0].to_string())
-  });
+      .collect::<Vec<_>>();
+    collisions.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+    collisions
+  }
 
-  if DATA.is_match(&result) {
-    return result.to_string();
+  /// The ident of each local by the path `path_of` returns for its module, sorted to serialize
+  /// them in a stable order, e.g. for tools renaming the classes of html templates after a build.
+  /// Modules `path_of` has no path for, e.g. removed from the compilation, are skipped.
+  pub fn manifest(
+    &self,
+    path_of: impl Fn(&ModuleIdentifier) -> Option<String>,
+  ) -> BTreeMap<String, BTreeMap<String, String>> {
+    let idents = self.idents.lock().expect("should lock the local idents");
+    idents
+      .iter()
+      .filter_map(|(module, idents)| {
+        let idents = idents
+          .iter()
+          .map(|(local, ident)| (local.to_string(), ident.to_string()))
+          .collect();
+        Some((path_of(module)?, idents))
+      })
+      .collect()
+  }
+}
+
+/// The `[hash]` computed for each local of the css modules of a compiler with
+/// [CssConfig::record_hashes], e.g. to build subresource integrity metadata once it's done.
+///
+/// [CssConfig::record_hashes]: crate::plugin::CssConfig::record_hashes
+#[derive(Debug, Default)]
+pub struct LocalHashCollector {
+  /// The hash of each local of each module, replaced when the module is parsed again.
+  hashes: Mutex<HashMap<ModuleIdentifier, IndexMap<JsWord, String>>>,
+}
+
+impl LocalHashCollector {
+  pub fn record(&self, module: ModuleIdentifier, hashes: IndexMap<JsWord, String>) {
+    self
+      .hashes
+      .lock()
+      .expect("should lock the local hashes")
+      .insert(module, hashes);
+  }
+
+  /// Forget the hashes of the modules `is_live` rejects, e.g. deleted or renamed since the last
+  /// compilation, so they don't pile up over the rebuilds of a watch session.
+  pub fn retain(&self, is_live: impl Fn(&ModuleIdentifier) -> bool) {
+    self
+      .hashes
+      .lock()
+      .expect("should lock the local hashes")
+      .retain(|module, _| is_live(module));
+  }
+
+  /// The hash of each local of `module`, in the order they were renamed in.
+  #[allow(clippy::unwrap_in_result)]
+  pub fn hashes(&self, module: &ModuleIdentifier) -> Option<IndexMap<JsWord, String>> {
+    self
+      .hashes
+      .lock()
+      .expect("should lock the local hashes")
+      .get(module)
+      .cloned()
+  }
+}
+
+/// The exports code rendered for the css modules of a compiler, shared by its generators so a module
+/// rendered again unchanged, e.g. when rebuilding in watch mode, reuses it.
+#[derive(Debug, Default)]
+pub struct ExportsCache {
+  /// The key, code and runtime requirements of each module, replaced when its key changes.
+  exports: Mutex<HashMap<ModuleIdentifier, (u64, String, RuntimeGlobals)>>,
+}
+
+impl ExportsCache {
+  /// The code cached for `module` under `key`, else the code `render` returns, cached in its place.
+  #[allow(clippy::unwrap_in_result)]
+  pub fn get_or_render(
+    &self,
+    module: ModuleIdentifier,
+    key: u64,
+    runtime_requirements: &mut RuntimeGlobals,
+    render: impl FnOnce(&mut RuntimeGlobals) -> Result<String>,
+  ) -> Result<String> {
+    {
+      let exports = self.exports.lock().expect("should lock the exports cache");
+      if let Some((cached_key, code, cached_runtime_requirements)) = exports.get(&module)
+        && *cached_key == key
+      {
+        runtime_requirements.insert(*cached_runtime_requirements);
+        return Ok(code.clone());
+      }
+    }
+    let mut rendered_runtime_requirements = RuntimeGlobals::default();
+    let code = render(&mut rendered_runtime_requirements)?;
+    runtime_requirements.insert(rendered_runtime_requirements);
+    self
+      .exports
+      .lock()
+      .expect("should lock the exports cache")
+      .insert(module, (key, code.clone(), rendered_runtime_requirements));
+    Ok(code)
+  }
+
+  /// Forget the code of the modules `is_live` rejects, e.g. deleted or renamed since the last
+  /// compilation, so it doesn't pile up over the rebuilds of a watch session.
+  pub fn retain(&self, is_live: impl Fn(&ModuleIdentifier) -> bool) {
+    self
+      .exports
+      .lock()
+      .expect("should lock the exports cache")
+      .retain(|module, _| is_live(module));
+  }
+}
+
+/// How many times the hot paths of the css modules of a compilation ran, only counted with
+/// [CssConfig::debug_counters] and reported in a `css_modules_counters` tracing span once the
+/// compilation is sealed. Parsers and generators share it, each gets `None` otherwise and counts
+/// nothing.
+///
+/// [CssConfig::debug_counters]: crate::plugin::CssConfig::debug_counters
+#[derive(Debug, Default)]
+pub struct TransformCounters {
+  new_name_for: AtomicUsize,
+  fast_urls: AtomicUsize,
+  slow_urls: AtomicUsize,
+  composed_imports: AtomicUsize,
+}
+
+/// The counts of [TransformCounters] at one point.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransformCountersSnapshot {
+  /// The locals renamed, each time the module they're in is parsed.
+  pub new_name_for: usize,
+  /// The `url()`s already normalized, returned as is by [normalize_url_with_options].
+  pub fast_urls: usize,
+  /// The ones it had to look into further, to trim, unquote, unescape or decode them.
+  pub slow_urls: usize,
+  /// The classes composed from other css modules, each time the exports composing them are
+  /// generated and their module is looked up.
+  pub composed_imports: usize,
+}
+
+impl TransformCounters {
+  pub fn snapshot(&self) -> TransformCountersSnapshot {
+    TransformCountersSnapshot {
+      new_name_for: self.new_name_for.load(Ordering::Relaxed),
+      fast_urls: self.fast_urls.load(Ordering::Relaxed),
+      slow_urls: self.slow_urls.load(Ordering::Relaxed),
+      composed_imports: self.composed_imports.load(Ordering::Relaxed),
+    }
+  }
+
+  /// Start counting again, e.g. for the next compilation of a watching compiler.
+  pub fn reset(&self) {
+    for counter in [
+      &self.new_name_for,
+      &self.fast_urls,
+      &self.slow_urls,
+      &self.composed_imports,
+    ] {
+      counter.store(0, Ordering::Relaxed);
+    }
   }
-  if result.contains('%') {
-    if let Ok(r) = urlencoding::decode(&result) {
-      return r.to_string();
+
+  pub(crate) fn count_new_name_for(&self) {
+    self.new_name_for.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub(crate) fn count_url(&self, fast: bool) {
+    if fast {
+      &self.fast_urls
+    } else {
+      &self.slow_urls
     }
+    .fetch_add(1, Ordering::Relaxed);
+  }
+
+  /// Count the classes `exports` compose from other css modules, before generating them.
+  pub(crate) fn count_composed_imports(&self, exports: &IndexMap<JsWord, Vec<CssClassName>>) {
+    let count = exports
+      .values()
+      .flatten()
+      .filter(|name| matches!(name, CssClassName::Import { .. }))
+      .count();
+    self.composed_imports.fetch_add(count, Ordering::Relaxed);
+  }
+}
+
+/// The classes a css module selects in global scope, e.g. `.a :global(.b)`, each counted once.
+/// Stored in the data of the code generation result of every css module, so a lint rule can tell
+/// which ones style global classes.
+#[derive(Debug, Clone)]
+pub struct CodeGenerationDataGlobalClasses {
+  inner: Vec<JsWord>,
+}
+
+impl CodeGenerationDataGlobalClasses {
+  pub fn new(inner: Vec<JsWord>) -> Self {
+    Self { inner }
+  }
+
+  pub fn inner(&self) -> &[JsWord] {
+    &self.inner
   }
 
-  result.to_string()
+  pub fn is_empty(&self) -> bool {
+    self.inner.is_empty()
+  }
+
+  pub fn len(&self) -> usize {
+    self.inner.len()
+  }
+}
+
+/// Errors for the locals of the css modules of `compilation` [collected](LocalIdentCollector) with
+/// the same ident, their rules would style each other's elements.
+pub(crate) fn local_ident_collision_errors(
+  compilation: &Compilation,
+  idents: &LocalIdentCollector,
+) -> Vec<Diagnostic> {
+  let module_graph = &compilation.module_graph;
+  idents
+    .collisions(|module| module_graph.module_by_identifier(module).is_some())
+    .into_iter()
+    .map(|(ident, locals)| {
+      let locals = locals
+        .iter()
+        .map(|(module, local)| {
+          let module = module_graph
+            .module_by_identifier(module)
+            .expect("should have module")
+            .readable_identifier(&compilation.options.context);
+          format!("`{local}` of file: {module}")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+      Diagnostic::error(
+        "CSS Modules".to_string(),
+        format!(
+          "The locals {locals} are all renamed to `{ident}`, use a longer `hashDigestLength` or add `[local]` to `localIdentName` to tell them apart."
+        ),
+        0,
+        0,
+      )
+    })
+    .collect()
+}
+
+/// The JSON manifest of the locals of the css modules of `compilation` [collected](LocalIdentCollector)
+/// with their idents, see [LocalIdentCollector::manifest].
+pub(crate) fn local_ident_manifest(
+  compilation: &Compilation,
+  idents: &LocalIdentCollector,
+) -> Result<String> {
+  let manifest = idents.manifest(|module| {
+    compilation
+      .module_graph
+      .module_by_identifier(module)
+      .map(|module| {
+        module
+          .readable_identifier(&compilation.options.context)
+          .to_string()
+      })
+  });
+  serde_json::to_string_pretty(&manifest).map_err(|e| internal_error!(e.to_string()))
+}
+
+fn is_missing_module(module: &dyn Module) -> bool {
+  module.downcast_ref::<MissingModule>().is_some()
+}
+
+/// Whether a module of `module_type` requested with `query` is processed as a css module: a
+/// `?modules` or `?modules=false` query overrides the module type of a css file per import, e.g.
+/// `import styles from "./a.css?modules"`.
+pub fn is_css_modules_request(module_type: &ModuleType, query: Option<&str>) -> bool {
+  if !module_type.is_css_like() {
+    return false;
+  }
+  query
+    .and_then(|query| {
+      query
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|param| match param.split_once('=') {
+          None if param == "modules" => Some(true),
+          Some(("modules", "" | "true")) => Some(true),
+          Some(("modules", "false")) => Some(false),
+          _ => None,
+        })
+    })
+    .unwrap_or(module_type == &ModuleType::CssModule)
+}
+
+/// Like [is_css_modules_request], of a module in the module graph.
+pub(crate) fn is_css_module(module: &dyn Module) -> bool {
+  let query = module
+    .downcast_ref::<NormalModule>()
+    .and_then(|module| module.resource_resolved_data().resource_query.as_deref());
+  is_css_modules_request(module.module_type(), query)
+}
+
+/// Warnings for the cycles of css modules composing classes from each other, like `a.css` composing
+/// from `b.css` which composes back from `a.css`. The modules of a cycle export their composed
+/// classes lazily, see [write_css_modules_exports].
+pub(crate) fn compose_cycle_warnings(compilation: &Compilation) -> Vec<Diagnostic> {
+  fn visit<'a>(
+    compilation: &'a Compilation,
+    module: &'a dyn Module,
+    path: &mut Vec<&'a dyn Module>,
+    visited: &mut IdentifierSet,
+    warnings: &mut Vec<Diagnostic>,
+  ) {
+    visited.insert(module.identifier());
+    path.push(module);
+    for from in composed_css_modules(compilation, module) {
+      if let Some(start) = path
+        .iter()
+        .position(|module| module.identifier() == from.identifier())
+      {
+        let cycle = path[start..]
+          .iter()
+          .chain([&from])
+          .map(|module| module.readable_identifier(&compilation.options.context))
+          .collect::<Vec<_>>()
+          .join(" -> ");
+        warnings.push(Diagnostic::warn(
+          "CSS Modules".to_string(),
+          format!(
+            "file: {} `composes` forms a cycle: {cycle}, the classes composed within it are exported as getters to break it.",
+            path[start].readable_identifier(&compilation.options.context)
+          ),
+          0,
+          0,
+        ));
+      } else if !visited.contains(&from.identifier()) {
+        visit(compilation, from, path, visited, warnings);
+      }
+    }
+    path.pop();
+  }
+
+  // Walked in order of identifier, so a cycle is always reported from the same module.
+  let mut modules = compilation
+    .module_graph
+    .modules()
+    .values()
+    .filter(|module| is_css_module(module.as_ref()))
+    .map(|module| module.as_ref())
+    .collect::<Vec<_>>();
+  modules.sort_by(|a, b| a.identifier().as_str().cmp(b.identifier().as_str()));
+  let mut visited = IdentifierSet::default();
+  let mut warnings = vec![];
+  for module in modules {
+    if !visited.contains(&module.identifier()) {
+      visit(
+        compilation,
+        module,
+        &mut vec![],
+        &mut visited,
+        &mut warnings,
+      );
+    }
+  }
+  warnings
+}
+
+/// Whether `module` composes classes from a css module that composes, directly or not, back from
+/// `module`.
+fn is_in_compose_cycle(compilation: &Compilation, module: &dyn Module) -> bool {
+  let mut visited = IdentifierSet::default();
+  let mut queue = composed_css_modules(compilation, module);
+  while let Some(from) = queue.pop() {
+    if from.identifier() == module.identifier() {
+      return true;
+    }
+    if visited.insert(from.identifier()) {
+      queue.extend(composed_css_modules(compilation, from));
+    }
+  }
+  false
+}
+
+/// The css modules `module` composes classes from, in order of identifier.
+fn composed_css_modules<'a>(
+  compilation: &'a Compilation,
+  module: &dyn Module,
+) -> Vec<&'a dyn Module> {
+  let mut modules = composed_modules_by_request(compilation, module)
+    .into_values()
+    .filter(|from| is_css_module(*from))
+    .collect::<Vec<_>>();
+  modules.sort_by(|a, b| a.identifier().as_str().cmp(b.identifier().as_str()));
+  // Different requests may resolve to the same module.
+  modules.dedup_by_key(|module| module.identifier());
+  modules
+}
+
+/// With `user_requests`, the modules are keyed by the user request of the dependencies too,
+/// e.g. the request as written before a `beforeResolve` hook rewrote it.
+fn modules_by_request<'a>(
+  compilation: &'a Compilation,
+  module: &dyn Module,
+  filter: impl Fn(&dyn ModuleDependency) -> bool,
+  user_requests: bool,
+) -> HashMap<&'a str, &'a dyn Module> {
+  let module_graph = &compilation.module_graph;
+  let mut modules = HashMap::default();
+  if let Some(mgm) = module_graph.module_graph_module_by_identifier(&module.identifier()) {
+    for id in &mgm.dependencies {
+      if let Some(dependency) = module_graph.dependency_by_id(id)
+        && filter(dependency.as_ref())
+        && let Some(module) = module_graph
+          .module_graph_module_by_dependency_id(id)
+          .and_then(|mgm| module_graph.module_by_identifier(&mgm.module_identifier))
+      {
+        modules
+          .entry(dependency.request())
+          .or_insert(module.as_ref());
+        if user_requests && dependency.user_request() != dependency.request() {
+          modules
+            .entry(dependency.user_request())
+            .or_insert(module.as_ref());
+        }
+      }
+    }
+  }
+  modules
+}
+
+/// The media query lists the rules of `module` apply under, outermost first, as conditioned by the
+/// `@import "a.css" print;` of it. Empty when it's imported unconditionally at least once, e.g. from
+/// js or by an `@import` without media queries.
+///
+/// Imported under different media query lists, the rules apply under any of them. The lists its
+/// importers are imported under are nested around only when they agree, so a stylesheet imported
+/// by a printed and a screen only one isn't hidden from either.
+pub fn import_media(compilation: &Compilation, module: &dyn Module) -> Vec<String> {
+  fn visit(
+    compilation: &Compilation,
+    identifier: ModuleIdentifier,
+    visiting: &mut IdentifierSet,
+  ) -> Vec<String> {
+    let module_graph = &compilation.module_graph;
+    let Some(mgm) = module_graph.module_graph_module_by_identifier(&identifier) else {
+      return vec![];
+    };
+    let Ok(connections) = mgm.incoming_connections_unordered(module_graph) else {
+      return vec![];
+    };
+    // Met again through an `@import` cycle, which doesn't condition the rules any further.
+    if !visiting.insert(identifier) {
+      return vec![];
+    }
+    let mut media = vec![];
+    let mut importers = vec![];
+    for connection in connections {
+      let dependency_media = module_graph
+        .dependency_by_id(&connection.dependency_id)
+        .and_then(|dependency| dependency.as_any().downcast_ref::<CssImportDependency>())
+        .and_then(|dependency| dependency.media());
+      match (dependency_media, connection.original_module_identifier) {
+        (Some(dependency_media), Some(importer)) => {
+          media.push(dependency_media);
+          importers.push(importer);
+        }
+        _ => {
+          visiting.remove(&identifier);
+          return vec![];
+        }
+      }
+    }
+    if media.is_empty() {
+      visiting.remove(&identifier);
+      return vec![];
+    }
+    media.sort_unstable();
+    media.dedup();
+    importers.sort_unstable();
+    importers.dedup();
+    let outer = importers
+      .into_iter()
+      .map(|importer| visit(compilation, importer, visiting))
+      .collect::<Vec<_>>();
+    visiting.remove(&identifier);
+    let mut layers = if outer.windows(2).all(|pair| pair[0] == pair[1]) {
+      outer.into_iter().next().unwrap_or_default()
+    } else {
+      vec![]
+    };
+    layers.push(media.join(", "));
+    layers
+  }
+
+  visit(
+    compilation,
+    module.identifier(),
+    &mut IdentifierSet::default(),
+  )
+}
+
+static JS_IDENTIFIER: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"^[A-Za-z_$][A-Za-z0-9_$]*$").expect("Invalid RegExp"));
+
+/// Reserved words of strict mode code, which can't be imported by name.
+const JS_RESERVED_WORDS: &[&str] = &[
+  "await",
+  "break",
+  "case",
+  "catch",
+  "class",
+  "const",
+  "continue",
+  "debugger",
+  "default",
+  "delete",
+  "do",
+  "else",
+  "enum",
+  "export",
+  "extends",
+  "false",
+  "finally",
+  "for",
+  "function",
+  "if",
+  "implements",
+  "import",
+  "in",
+  "instanceof",
+  "interface",
+  "let",
+  "new",
+  "null",
+  "package",
+  "private",
+  "protected",
+  "public",
+  "return",
+  "static",
+  "super",
+  "switch",
+  "this",
+  "throw",
+  "true",
+  "try",
+  "typeof",
+  "var",
+  "void",
+  "while",
+  "with",
+  "yield",
+];
+
+/// Keys which can't be written as `export const key`, are only reachable from the default export.
+pub(crate) fn is_named_export(key: &str) -> bool {
+  JS_IDENTIFIER.is_match(key) && !JS_RESERVED_WORDS.contains(&key)
+}
+
+/// Warn about the keys `namedExport` has to drop, since they aren't valid named exports.
+pub(crate) fn invalid_named_export_warnings(
+  exports: &IndexMap<JsWord, Vec<CssClassName>>,
+  locals_convention: &LocalsConvention,
+  resource_path: &Path,
+) -> Vec<Diagnostic> {
+  group_exports_by_convention(exports, locals_convention)
+    .keys()
+    .filter(|key| !is_named_export(key))
+    .map(|key| {
+      Diagnostic::warn(
+        "CSS Modules".to_string(),
+        format!(
+          "file: {} `{key}` is not a valid named export and is not exported, rename the class or disable `namedExport` to access it from the default export.",
+          resource_path.display()
+        ),
+        0,
+        0,
+      )
+    })
+    .collect()
+}
+
+/// Quote `s` as a JS string literal that is safe to inline in html, `<` and `>` are escaped so
+/// neither `</script>`, `</style>` nor `<!--` can show up in the output.
+fn to_js_string(s: &str, single_quote: bool) -> Result<String> {
+  let json = serde_json::to_string(s).map_err(|e| internal_error!(e.to_string()))?;
+  let json = if json.contains(['<', '>']) {
+    json.replace('<', "\\u003c").replace('>', "\\u003e")
+  } else {
+    json
+  };
+  Ok(if single_quote {
+    to_single_quoted(&json)
+  } else {
+    json
+  })
+}
+
+/// Turn a double quoted JSON string into a single quoted js string of the same value, `"` no
+/// longer needs escaping and `'` now does.
+fn to_single_quoted(json: &str) -> String {
+  let mut quoted = String::with_capacity(json.len());
+  quoted.push('\'');
+  let mut chars = json[1..json.len() - 1].chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => match chars.next() {
+        Some('"') => quoted.push('"'),
+        Some(escaped) => {
+          quoted.push('\\');
+          quoted.push(escaped);
+        }
+        None => quoted.push('\\'),
+      },
+      '\'' => quoted.push_str("\\'"),
+      c => quoted.push(c),
+    }
+  }
+  quoted.push('\'');
+  quoted
+}
+
+/// Join the class names bound to an export key into a js expression.
+///
+/// Composed class names are only known at runtime and may be empty, or even `undefined` for
+/// a missing class, these are skipped instead of leaving a dangling separator.
+fn join_class_names(values: &[ExportValue], separator: &str, single_quote: bool) -> Result<String> {
+  if values
+    .iter()
+    .all(|value| matches!(value, ExportValue::Static(_)))
+  {
+    let joined = values
+      .iter()
+      .filter_map(|value| match value {
+        ExportValue::Static(name) => Some(name.as_str()),
+        ExportValue::Composed(_) => None,
+      })
+      .collect::<Vec<_>>()
+      .join(separator);
+    return to_js_string(&joined, single_quote);
+  }
+  let values = values
+    .iter()
+    .map(|value| match value {
+      ExportValue::Static(name) => to_js_string(name, single_quote),
+      ExportValue::Composed(expr) => Ok(expr.clone()),
+    })
+    .collect::<Result<Vec<_>>>()?
+    .join(", ");
+  Ok(format!(
+    "[{values}].filter(Boolean).join({})",
+    to_js_string(separator, single_quote)?
+  ))
+}
+
+/// The key a composed class name is exported under by the css module it's imported from.
+fn imported_export_key(name: &str, locals_convention: &LocalsConvention) -> String {
+  locals_convention
+    .keys(name)
+    .next()
+    .unwrap_or_else(|| name.to_string())
+}
+
+/// Map `exports` to the keys emitted under `locals_convention`.
+///
+/// Every emitted key appears exactly once. When several exports end up with the same key,
+/// e.g. `foo` under both `asIs` and `camelCase`, or `foo-bar` camelCased next to an existing
+/// `fooBar`, their class names are merged in source order.
+///
+/// Keys follow the order of `exports`, and the keys of one export are emitted as-is, then
+/// camelCase, then dashes, see [`LocalsConvention::keys`].
+pub(crate) fn group_exports_by_convention<'a>(
+  exports: impl IntoIterator<Item = (&'a JsWord, &'a Vec<CssClassName>)>,
+  locals_convention: &LocalsConvention,
+) -> IndexMap<String, Vec<&'a CssClassName>> {
+  let mut grouped: IndexMap<String, Vec<&CssClassName>> = IndexMap::default();
+  for (key, elements) in exports {
+    for key in locals_convention.keys(key) {
+      let group = grouped.entry(key).or_default();
+      for element in elements {
+        if !group.contains(&element) {
+          group.push(element);
+        }
+      }
+    }
+  }
+  grouped
+}
+
+/// A `\` escaped line break of a string, `\r\n` is a single line break.
+static STRING_MULTILINE: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"\\(?:\r\n|[\n\r\f])").expect("Invalid RegExp"));
+
+static DATA: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(?i)data:").expect("Invalid RegExp"));
+
+static URL_SCHEME: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"^[a-zA-Z][a-zA-Z\d+\-.]*:").expect("Invalid RegExp"));
+
+/// Apply `f` to `s`, keeping `s` (and its borrow) when `f` left it unchanged.
+fn map_cow(s: Cow<'_, str>, f: impl FnOnce(&str) -> Cow<'_, str>) -> Cow<'_, str> {
+  let changed = match f(&s) {
+    Cow::Borrowed(_) => None,
+    Cow::Owned(owned) => Some(owned),
+  };
+  changed.map_or(s, Cow::Owned)
+}
+
+fn is_css_white_space(c: char) -> bool {
+  matches!(c, ' ' | '\t' | '\n' | '\r' | '\x0C')
+}
+
+/// NUL and the C0 control characters other than white space, replaced with U+FFFD in urls.
+fn is_replaced_control(c: char) -> bool {
+  c.is_ascii_control() && c != '\x7F' && !is_css_white_space(c)
+}
+
+/// Replace the css escapes in `s` with the characters they stand for, borrowing `s` when it has
+/// none.
+///
+/// Following the css syntax spec a `\` and 1 to 6 hex digits, optionally ended by a single white
+/// space, is the code point of that number, U+FFFD when it is zero, a surrogate or out of range.
+/// A `\` before any other character is that character. A `\` ending `s` escapes nothing and is
+/// kept.
+pub fn css_unescape(s: &str) -> Cow<'_, str> {
+  css_unescape_with(s, InvalidEscape::Replace)
+}
+
+/// Like [css_unescape], with `invalid_escape` deciding what the escape of a zero, surrogate or out
+/// of range code point becomes.
+pub fn css_unescape_with(s: &str, invalid_escape: InvalidEscape) -> Cow<'_, str> {
+  let Some(start) = s.find('\\') else {
+    return Cow::Borrowed(s);
+  };
+  let mut result = String::with_capacity(s.len());
+  result.push_str(&s[..start]);
+  let rest = &s[start..];
+  let mut chars = rest.char_indices().peekable();
+  while let Some((index, c)) = chars.next() {
+    if c != '\\' {
+      result.push(c);
+      continue;
+    }
+    let Some(&(_, next)) = chars.peek() else {
+      result.push('\\');
+      break;
+    };
+    if !next.is_ascii_hexdigit() {
+      result.push(next);
+      chars.next();
+      continue;
+    }
+    let mut code_point = 0;
+    for _ in 0..6 {
+      match chars.peek().and_then(|(_, c)| c.to_digit(16)) {
+        Some(digit) => {
+          code_point = code_point * 16 + digit;
+          chars.next();
+        }
+        None => break,
+      }
+    }
+    // `\r\n` counts as a single white space.
+    if chars.next_if(|&(_, c)| c == '\r').is_some() {
+      chars.next_if(|&(_, c)| c == '\n');
+    } else {
+      chars.next_if(|&(_, c)| is_css_white_space(c));
+    }
+    match (
+      char::from_u32(code_point).filter(|&c| c != '\0'),
+      invalid_escape,
+    ) {
+      (Some(c), _) => result.push(c),
+      (None, InvalidEscape::Replace) => result.push(char::REPLACEMENT_CHARACTER),
+      (None, InvalidEscape::Keep) => {
+        let end = chars.peek().map_or(rest.len(), |&(end, _)| end);
+        result.push_str(&rest[index..end]);
+      }
+    }
+  }
+  Cow::Owned(result)
+}
+
+/// Plain urls such as `./img.png` need no transformation and are borrowed from `s`.
+///
+/// Like the css tokenizer does, a literal NUL or C0 control character other than white space is
+/// replaced with U+FFFD, data URIs included.
+pub fn normalize_url(s: &str) -> Cow<'_, str> {
+  normalize_url_with_diagnostics(s).0
+}
+
+/// Like [normalize_url], with a warning for each part of a malformed url it can only normalize on
+/// a best-effort basis: an empty url, a line break that isn't escaped and a percent escape that
+/// can't be decoded.
+///
+/// The diagnostics carry no location, callers attach the module and span of the `url()`.
+pub fn normalize_url_with_diagnostics(s: &str) -> (Cow<'_, str>, Vec<Diagnostic>) {
+  normalize_url_with_options(s, NormalizeUrlOptions::default())
+}
+
+/// Like [normalize_url_with_diagnostics], but the Unicode white space and byte order marks around
+/// the url are trimmed too, e.g. `\u{FEFF}img.png` or `\u{A0}img.png\u{A0}`.
+pub fn normalize_lenient_url_with_diagnostics(s: &str) -> (Cow<'_, str>, Vec<Diagnostic>) {
+  normalize_url_with_options(
+    s,
+    NormalizeUrlOptions {
+      lenient: true,
+      ..Default::default()
+    },
+  )
+}
+
+/// How [normalize_url_with_options] normalizes urls, by default like
+/// [normalize_url_with_diagnostics].
+#[derive(Debug, Clone, Copy)]
+pub struct NormalizeUrlOptions {
+  /// Like [normalize_lenient_url_with_diagnostics].
+  pub lenient: bool,
+  /// Decode the percent escapes of the path, e.g. `foo%20bar.png` to `foo bar.png`. Off, the url
+  /// of an asset whose name is percent-encoded stays as written, but is still trimmed and
+  /// unescaped.
+  pub percent_decode: bool,
+}
+
+impl Default for NormalizeUrlOptions {
+  fn default() -> Self {
+    Self {
+      lenient: false,
+      percent_decode: true,
+    }
+  }
+}
+
+/// Whether [normalize_url_with_options] returns `s` as is without looking further: without a
+/// backslash, a `%`, a line break, a control character, a leading quote or surrounding white
+/// space every pass it makes is a no-op.
+pub(crate) fn is_normalized_url(s: &str, options: NormalizeUrlOptions) -> bool {
+  let is_trimmed =
+    |c: char| is_css_white_space(c) || options.lenient && (c.is_whitespace() || c == '\u{FEFF}');
+  !s.is_empty()
+    && !s.contains(|c| matches!(c, '\\' | '%' | '\n' | '\r' | '\x0C') || is_replaced_control(c))
+    && !s.starts_with(['"', '\''])
+    && !s.starts_with(is_trimmed)
+    && !s.ends_with(is_trimmed)
+}
+
+/// Like [normalize_url_with_diagnostics], with `options`.
+pub fn normalize_url_with_options(
+  s: &str,
+  options: NormalizeUrlOptions,
+) -> (Cow<'_, str>, Vec<Diagnostic>) {
+  let is_trimmed =
+    |c: char| is_css_white_space(c) || options.lenient && (c.is_whitespace() || c == '\u{FEFF}');
+  let mut diagnostics = vec![];
+  let mut warn = |message: String| {
+    diagnostics.push(
+      Diagnostic::warn("Malformed url".to_string(), message, 0, 0).with_kind(DiagnosticKind::Css),
+    )
+  };
+  if is_normalized_url(s, options) {
+    return (Cow::Borrowed(s), diagnostics);
+  }
+  let result = if s.contains(is_replaced_control) {
+    Cow::Owned(s.replace(is_replaced_control, "\u{FFFD}"))
+  } else {
+    Cow::Borrowed(s)
+  };
+  let result = map_cow(result, |s| STRING_MULTILINE.replace_all(s, ""));
+  let result = match result {
+    Cow::Borrowed(result) => Cow::Borrowed(result.trim_matches(is_trimmed)),
+    Cow::Owned(result) => Cow::Owned(result.trim_matches(is_trimmed).to_string()),
+  };
+  // `url("a.png")` and `url('a.png')` are the same url as `url(a.png)`.
+  if result.starts_with(['"', '\'']) && strip_quotes(&result).is_none() {
+    warn(format!(
+      "`url({s})` has a quote that isn't closed, it's kept in the url as is."
+    ));
+  }
+  let result = match result {
+    Cow::Borrowed(result) => Cow::Borrowed(strip_quotes(result).unwrap_or(result)),
+    Cow::Owned(result) => match strip_quotes(&result) {
+      Some(unquoted) => Cow::Owned(unquoted.to_string()),
+      None => Cow::Owned(result),
+    },
+  };
+  if result.is_empty() {
+    warn(format!(
+      "`url({s})` is empty, it refers to the stylesheet itself."
+    ));
+    return (result, diagnostics);
+  }
+  if result.contains(['\n', '\r', '\x0C']) {
+    warn(format!(
+      "`url({})` contains a line break that isn't escaped, it's kept in the url as is.",
+      s.escape_debug()
+    ));
+  }
+  // Data URIs are kept verbatim, neither unescaped nor percent-decoded.
+  if DATA.is_match(&result) {
+    return (result, diagnostics);
+  }
+  let result = map_cow(result, css_unescape);
+
+  // Only the path is decoded, `?query` and `#fragment` are reattached untouched.
+  let (path, suffix) = result
+    .find(['?', '#'])
+    .map_or((result.as_ref(), ""), |index| result.split_at(index));
+  if options.percent_decode && path.contains('%') {
+    let bytes = path.as_bytes();
+    let is_escape = |index: usize| {
+      bytes.len() > index + 2
+        && bytes[index + 1].is_ascii_hexdigit()
+        && bytes[index + 2].is_ascii_hexdigit()
+    };
+    if path.match_indices('%').any(|(index, _)| !is_escape(index)) {
+      warn(format!(
+        "`url({path})` has a `%` that doesn't start a percent escape, it's kept in the url as is."
+      ));
+    }
+    // Literal `%`s only, e.g. `width%.png`, there's nothing to decode.
+    if path.match_indices('%').any(|(index, _)| is_escape(index)) {
+      let path = urlencoding::decode(path)
+        .map(|r| r.into_owned())
+        .unwrap_or_else(|_| {
+          warn(format!(
+            "`url({path})` has percent escapes that don't decode to UTF-8, they're kept in the url as is."
+          ));
+          decode_valid_escapes(path)
+        });
+      return (Cow::Owned(format!("{path}{suffix}")), diagnostics);
+    }
+  }
+
+  (result, diagnostics)
+}
+
+/// `s` without its surrounding pair of matching quotes, `None` when `s` isn't quoted or the closing
+/// quote is escaped, e.g. `"a\"`. The quotes within are left alone, they're unescaped with the rest.
+fn strip_quotes(s: &str) -> Option<&str> {
+  let quote = s.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+  let unquoted = s[1..].strip_suffix(quote)?;
+  let backslashes = unquoted.bytes().rev().take_while(|b| *b == b'\\').count();
+  (backslashes % 2 == 0).then_some(unquoted)
+}
+
+/// The module request a [normalized](normalize_url) `url()` of a stylesheet in `context` depends
+/// on, `None` for urls kept as is: data uris, urls with a scheme or protocol relative ones, root
+/// relative paths and fragments.
+///
+/// Like in css-loader a bare `img.png` is relative to the stylesheet and `~package/img.png` is a
+/// request of a package.
+pub fn url_to_dependency_request(url: &str, context: &Path) -> Option<String> {
+  if url.is_empty()
+    || DATA.is_match(url)
+    || URL_SCHEME.is_match(url)
+    || url.starts_with(['/', '#', '?'])
+  {
+    return None;
+  }
+  if let Some(request) = module_request(url) {
+    return Some(request.to_string());
+  }
+  let url = url.strip_prefix("./").unwrap_or(url);
+  Some(context.join(url).to_string_lossy().into_owned())
+}
+
+/// The package request of a webpack style `~package/file.css` request, `None` for any other
+/// request.
+///
+/// `~/file.css` looks like a path in the home directory rather than a package, it is left to be
+/// resolved as it was written.
+pub fn module_request(request: &str) -> Option<&str> {
+  request
+    .strip_prefix('~')
+    .filter(|request| !request.is_empty() && !request.starts_with('/'))
+}
+
+/// Percent-decode `s`, leaving escapes that don't form valid UTF-8 as they were written.
+fn decode_valid_escapes(s: &str) -> String {
+  fn hex(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+  }
+
+  let bytes = s.as_bytes();
+  let mut result = String::with_capacity(s.len());
+  let mut rest = s;
+  while let Some(index) = rest.find('%') {
+    result.push_str(&rest[..index]);
+    let offset = s.len() - rest.len() + index;
+    // Collect the run of consecutive `%XX` escapes, a multi-byte char spans several of them.
+    let mut end = offset;
+    let mut decoded = vec![];
+    while end + 2 < bytes.len() && bytes[end] == b'%' {
+      match (hex(bytes[end + 1]), hex(bytes[end + 2])) {
+        (Some(high), Some(low)) => decoded.push(high << 4 | low),
+        _ => break,
+      }
+      end += 3;
+    }
+    if decoded.is_empty() {
+      result.push('%');
+      rest = &s[offset + 1..];
+      continue;
+    }
+    let mut start = 0;
+    while start < decoded.len() {
+      match std::str::from_utf8(&decoded[start..]) {
+        Ok(valid) => {
+          result.push_str(valid);
+          break;
+        }
+        Err(e) => {
+          let valid_up_to = start + e.valid_up_to();
+          result.push_str(std::str::from_utf8(&decoded[start..valid_up_to]).expect("valid utf8"));
+          let invalid_len = e.error_len().unwrap_or(decoded.len() - valid_up_to);
+          let invalid = offset + valid_up_to * 3;
+          result.push_str(&s[invalid..invalid + invalid_len * 3]);
+          start = valid_up_to + invalid_len;
+        }
+      }
+    }
+    rest = &s[end..];
+  }
+  result.push_str(rest);
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{path::PathBuf, str::FromStr, sync::Arc};
+
+  use rspack_core::RawModule;
+  use rspack_hash::HashFunction;
+  use swc_core::{
+    common::{FileName, SourceMap, Span, DUMMY_SP},
+    css::{
+      ast::{Ident, Stylesheet},
+      modules::compile,
+      parser::parser::ParserConfig,
+    },
+    ecma::parser::{Parser, StringInput},
+  };
+
+  use super::*;
+  use crate::{
+    plugin::LineEnding,
+    swc_css_compiler::{SwcCssSourceMapGenConfig, SWC_COMPILER},
+    visitors::scope_locals,
+  };
+
+  fn local(name: &str) -> CssClassName {
+    CssClassName::Local {
+      name: Ident {
+        span: DUMMY_SP,
+        value: name.into(),
+        raw: None,
+      },
+    }
+  }
+
+  fn exports(entries: &[(&str, &[&str])]) -> IndexMap<JsWord, Vec<CssClassName>> {
+    entries
+      .iter()
+      .map(|(key, names)| ((*key).into(), names.iter().map(|n| local(n)).collect()))
+      .collect()
+  }
+
+  fn grouped(
+    exports: &IndexMap<JsWord, Vec<CssClassName>>,
+    locals_convention: &str,
+  ) -> Vec<(String, Vec<String>)> {
+    let locals_convention = LocalsConvention::from_str(locals_convention).expect("valid");
+    group_exports_by_convention(exports, &locals_convention)
+      .into_iter()
+      .map(|(key, elements)| {
+        let names = elements
+          .into_iter()
+          .map(|element| match element {
+            CssClassName::Local { name } => name.value.to_string(),
+            _ => unreachable!(),
+          })
+          .collect();
+        (key, names)
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_is_named_export() {
+    assert!(is_named_export("foo"));
+    assert!(is_named_export("fooBar"));
+    assert!(is_named_export("_foo$1"));
+    assert!(!is_named_export("foo-bar"));
+    assert!(!is_named_export("1foo"));
+    assert!(!is_named_export("default"));
+    for word in JS_RESERVED_WORDS {
+      assert!(!is_named_export(word), "{word}");
+    }
+    assert!(is_named_export("classes"));
+  }
+
+  #[test]
+  fn test_group_exports_unique_keys() {
+    let exports = exports(&[("foo", &["_foo"]), ("foo-bar", &["_foo-bar"])]);
+    assert_eq!(
+      grouped(&exports, "camelCase"),
+      vec![
+        ("foo".to_string(), vec!["_foo".to_string()]),
+        ("foo-bar".to_string(), vec!["_foo-bar".to_string()]),
+        ("fooBar".to_string(), vec!["_foo-bar".to_string()]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_group_exports_merge_colliding_keys() {
+    let exports = exports(&[("foo-bar", &["_foo-bar"]), ("fooBar", &["_fooBar"])]);
+    assert_eq!(
+      grouped(&exports, "camelCaseOnly"),
+      vec![(
+        "fooBar".to_string(),
+        vec!["_foo-bar".to_string(), "_fooBar".to_string()]
+      )]
+    );
+  }
+
+  #[test]
+  fn test_group_exports_camel_case_only_one_key_per_class() {
+    let exports = exports(&[("foo-bar", &["_foo-bar"]), ("baz", &["_baz"])]);
+    assert_eq!(
+      grouped(&exports, "camelCaseOnly"),
+      vec![
+        ("fooBar".to_string(), vec!["_foo-bar".to_string()]),
+        ("baz".to_string(), vec!["_baz".to_string()]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_group_exports_dashes_only_one_key_per_class() {
+    let exports = exports(&[("fooBar", &["_fooBar"]), ("baz_qux", &["_baz_qux"])]);
+    assert_eq!(
+      grouped(&exports, "dashesOnly"),
+      vec![
+        ("foo-bar".to_string(), vec!["_fooBar".to_string()]),
+        ("baz-qux".to_string(), vec!["_baz_qux".to_string()]),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_group_exports_emission_order() {
+    let exports = exports(&[("foo-bar", &["_foo-bar"]), ("foo_bar", &["_foo_bar"])]);
+    let keys = |convention| {
+      grouped(&exports, convention)
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(keys("camelCase"), vec!["foo-bar", "fooBar", "foo_bar"]);
+    assert_eq!(keys("dashes"), vec!["foo-bar", "foo_bar"]);
+    assert_eq!(
+      grouped(&exports, "dashes")[0],
+      (
+        "foo-bar".to_string(),
+        vec!["_foo-bar".to_string(), "_foo_bar".to_string()]
+      )
+    );
+  }
+
+  #[test]
+  fn test_imported_export_key() {
+    let convention = |s| LocalsConvention::from_str(s).expect("valid");
+    assert_eq!(
+      imported_export_key("foo-bar", &convention("camelCase")),
+      "foo-bar"
+    );
+    assert_eq!(
+      imported_export_key("foo-bar", &convention("camelCaseOnly")),
+      "fooBar"
+    );
+    assert_eq!(
+      imported_export_key("fooBar", &convention("dashesOnly")),
+      "foo-bar"
+    );
+  }
+
+  #[test]
+  fn test_normalize_url() {
+    assert_eq!(normalize_url("image%20a.png"), "image a.png");
+    assert_eq!(
+      normalize_url("image%20a.png?foo=%20#frag"),
+      "image a.png?foo=%20#frag"
+    );
+    assert_eq!(
+      normalize_url("image%20a.png#a%20b#c"),
+      "image a.png#a%20b#c"
+    );
+    assert_eq!(normalize_url("image.png?a=%23#b"), "image.png?a=%23#b");
+    assert_eq!(
+      normalize_url("data:image/png,%20?a#b"),
+      "data:image/png,%20?a#b"
+    );
+  }
+
+  #[test]
+  fn test_is_css_modules_request() {
+    for query in [
+      Some("?modules"),
+      Some("?modules="),
+      Some("?modules=true"),
+      Some("?a=1&modules"),
+    ] {
+      assert!(is_css_modules_request(&ModuleType::Css, query), "{query:?}");
+      assert!(
+        is_css_modules_request(&ModuleType::CssModule, query),
+        "{query:?}"
+      );
+    }
+    for query in [Some("?modules=false"), Some("?a&modules=false")] {
+      assert!(
+        !is_css_modules_request(&ModuleType::Css, query),
+        "{query:?}"
+      );
+      assert!(
+        !is_css_modules_request(&ModuleType::CssModule, query),
+        "{query:?}"
+      );
+    }
+    // Otherwise the module type decides.
+    for query in [
+      None,
+      Some("?"),
+      Some("?module"),
+      Some("?modules=global"),
+      Some("?amodules"),
+    ] {
+      assert!(
+        !is_css_modules_request(&ModuleType::Css, query),
+        "{query:?}"
+      );
+      assert!(
+        is_css_modules_request(&ModuleType::CssModule, query),
+        "{query:?}"
+      );
+    }
+    assert!(!is_css_modules_request(&ModuleType::Js, Some("?modules")));
+  }
+
+  #[test]
+  fn test_normalize_lenient_url() {
+    for url in [
+      "\u{FEFF}img.png",
+      "\u{A0}img.png\u{A0}",
+      " \u{FEFF}\u{A0}img.png\u{3000}\n",
+      "\u{FEFF}\"img.png\"",
+    ] {
+      let (normalized, diagnostics) = normalize_lenient_url_with_diagnostics(url);
+      assert!(matches!(normalized, Cow::Borrowed("img.png")), "{url:?}");
+      assert!(diagnostics.is_empty(), "{url:?}");
+      // Only ASCII white space is trimmed by default, as in CSS.
+      assert_ne!(normalize_url(url), "img.png", "{url:?}");
+    }
+    assert_eq!(normalize_url("\u{A0}img.png\u{A0}"), "\u{A0}img.png\u{A0}");
+    assert_eq!(normalize_url("\u{FEFF}img.png"), "\u{FEFF}img.png");
+    // White space within the url is kept.
+    assert_eq!(
+      normalize_lenient_url_with_diagnostics("\u{A0}a\u{A0}b.png ").0,
+      "a\u{A0}b.png"
+    );
+    let (normalized, diagnostics) = normalize_lenient_url_with_diagnostics("\u{FEFF}\u{A0}");
+    assert_eq!(normalized, "");
+    assert_eq!(diagnostics.len(), 1);
+  }
+
+  #[test]
+  fn test_normalize_url_without_percent_decoding() {
+    let keep = NormalizeUrlOptions {
+      percent_decode: false,
+      ..Default::default()
+    };
+    let normalize = |url| normalize_url_with_options(url, keep);
+    assert_eq!(normalize_url("foo%20bar.png"), "foo bar.png");
+    assert_eq!(normalize("foo%20bar.png").0, "foo%20bar.png");
+    assert!(matches!(normalize("foo%20bar.png").0, Cow::Borrowed(_)));
+    // Still trimmed, unquoted and unescaped.
+    assert_eq!(normalize(" 'foo%20b\\61r.png' ").0, "foo%20bar.png");
+    // Only decoding warns about a `%` that isn't an escape.
+    let (normalized, diagnostics) = normalize("100%.png");
+    assert_eq!(normalized, "100%.png");
+    assert!(diagnostics.is_empty(), "{diagnostics:?}");
+    assert_eq!(
+      normalize_url_with_options(
+        "\u{A0}foo%20bar.png",
+        NormalizeUrlOptions {
+          lenient: true,
+          ..keep
+        }
+      )
+      .0,
+      "foo%20bar.png"
+    );
+  }
+
+  #[test]
+  fn test_normalize_url_control_characters() {
+    for (url, expected) in [
+      ("a\0b.png", "a\u{FFFD}b.png"),
+      ("\0", "\u{FFFD}"),
+      (
+        "a\x01\x08\x0B\x1F.png",
+        "a\u{FFFD}\u{FFFD}\u{FFFD}\u{FFFD}.png",
+      ),
+      ("\"a\0b.png\"", "a\u{FFFD}b.png"),
+      ("a\\\0.png", "a\u{FFFD}.png"),
+      ("data:text/plain,a\0", "data:text/plain,a\u{FFFD}"),
+      // White space and DEL aren't replaced.
+      ("a\tb\x7F.png", "a\tb\x7F.png"),
+    ] {
+      assert_eq!(normalize_url(url), expected, "{url:?}");
+    }
+    assert!(matches!(normalize_url("a\0.png"), Cow::Owned(_)));
+  }
+
+  #[test]
+  fn test_normalize_url_literal_percent() {
+    for url in ["width%.png", "100%zz.png", "a%2.png", "a%", "%%.png?a=%20"] {
+      let (normalized, diagnostics) = normalize_url_with_diagnostics(url);
+      // Kept without decoding and re-encoding, only warned about.
+      assert!(
+        matches!(normalized, Cow::Borrowed(u) if u == url),
+        "{url:?}"
+      );
+      assert_eq!(diagnostics.len(), 1, "{url:?}");
+    }
+    assert_eq!(normalize_url("width%.png"), "width%.png");
+    // Decoded as soon as a `%` starts an escape, the literal ones are kept.
+    assert_eq!(normalize_url("width%%20a.png"), "width% a.png");
+    assert_eq!(normalize_url("a%41%.png"), "aA%.png");
+  }
+
+  #[test]
+  fn test_normalize_url_quotes() {
+    for url in ["img.png", "'img.png'", "\"img.png\"", " \"img.png\"\n"] {
+      assert_eq!(normalize_url(url), "img.png", "{url:?}");
+    }
+    for (url, expected) in [
+      (r#""a\"b.png""#, r#"a"b.png"#),
+      (r#"'a\'b.png'"#, "a'b.png"),
+      (r#""a'b.png""#, "a'b.png"),
+      (r#"a"b".png"#, r#"a"b".png"#),
+      (r#""a%20b.png""#, "a b.png"),
+      (r#"'\69 mg.png'"#, "img.png"),
+      (
+        r#""data:image/svg+xml,<svg/>""#,
+        "data:image/svg+xml,<svg/>",
+      ),
+      // A single pair of quotes is stripped.
+      (r#""'img.png'""#, "'img.png'"),
+      // Escaped backslashes don't escape the closing quote.
+      (r#""a\\""#, r"a\"),
+    ] {
+      assert_eq!(normalize_url(url), expected, "{url:?}");
+    }
+    for (url, expected) in [
+      (r#""img.png'"#, r#""img.png'"#),
+      (r#""img.png\""#, r#""img.png""#),
+      ("'img.png", "'img.png"),
+    ] {
+      let (normalized, diagnostics) = normalize_url_with_diagnostics(url);
+      assert_eq!(normalized, expected, "{url:?}");
+      assert_eq!(diagnostics.len(), 1, "{url:?}");
+    }
+    assert!(matches!(
+      normalize_url(" 'img.png' "),
+      Cow::Borrowed("img.png")
+    ));
+  }
+
+  #[test]
+  fn test_normalize_url_quoted_white_space() {
+    assert_eq!(normalize_url(r#"" a.png ""#), " a.png ");
+    assert_eq!(normalize_url(r#" ' a.png '	"#), " a.png ");
+    assert_eq!(normalize_url(" a.png "), "a.png");
+
+    let source = r#"
+.a { background: url(" a.png "); }
+.b { background: url( b.png ); }
+"#;
+    let mut ss = SWC_COMPILER
+      .parse_file(
+        Default::default(),
+        "index.css",
+        source.to_string(),
+        Default::default(),
+      )
+      .expect("should parse")
+      .inner;
+    let deps = crate::visitors::analyze_dependencies(
+      &mut ss,
+      Path::new("/app/src"),
+      &mut vec![],
+      &mut vec![],
+      false,
+      Default::default(),
+      None,
+    );
+    let requests = deps.iter().map(|dep| dep.request()).collect::<Vec<_>>();
+    assert_eq!(requests, ["/app/src/ a.png ", "/app/src/b.png"]);
+  }
+
+  #[test]
+  fn test_normalize_url_line_continuations() {
+    for (url, expected) in [
+      ("a\\\r\nb", "ab"),
+      ("a\\\rb", "ab"),
+      ("a\\\nb", "ab"),
+      ("a\\\x0Cb", "ab"),
+      // Only a single line break is escaped.
+      ("a\\\r\n\nb", "a\nb"),
+      ("a\\\n\rb", "a\rb"),
+      ("a\\\r\n\\\r\nb", "ab"),
+    ] {
+      assert_eq!(normalize_url(url), expected, "{url:?}");
+    }
+  }
+
+  #[test]
+  fn test_normalize_url_diagnostics() {
+    for (url, expected) in [
+      ("", ""),
+      (" \t", ""),
+      ("a\nb.png", "a\nb.png"),
+      ("a\\\n\rb.png", "a\rb.png"),
+      ("a%2.png", "a%2.png"),
+      ("a%zz%20b.png", "a%zz b.png"),
+      ("a%.png?b=%", "a%.png?b=%"),
+      ("a%FF%20b.png", "a%FF b.png"),
+    ] {
+      let (normalized, diagnostics) = normalize_url_with_diagnostics(url);
+      assert_eq!(normalized, expected, "{url:?}");
+      assert_eq!(diagnostics.len(), 1, "{url:?}");
+      assert_eq!(diagnostics[0].kind, DiagnosticKind::Css);
+    }
+    for url in [
+      "img.png",
+      " img.png\n",
+      "a\\\nb.png",
+      "a%20b.png",
+      "a%25zz.png",
+      "a.png?b=%zz",
+      "data:image/png,%zz",
+    ] {
+      let (_, diagnostics) = normalize_url_with_diagnostics(url);
+      assert!(diagnostics.is_empty(), "{url:?}");
+    }
+  }
+
+  #[test]
+  fn test_normalize_url_borrows_plain_urls() {
+    for url in ["./img.png", "img.png?v=1#a", "data:image/png;base64,AAAA"] {
+      assert!(matches!(normalize_url(url), Cow::Borrowed(u) if u == url));
+    }
+    assert!(matches!(
+      normalize_url(" img.png\t"),
+      Cow::Borrowed("img.png")
+    ));
+    assert!(matches!(normalize_url("a\\.png"), Cow::Owned(_)));
+  }
+
+  #[test]
+  fn test_normalize_url_fast_path_only_skips_no_ops() {
+    for (url, expected) in [
+      ("img\\\n.png", "img.png"),
+      ("\\69 mg.png", "img.png"),
+      ("img\\(1\\).png", "img(1).png"),
+      ("\t img.png \n", "img.png"),
+      ("a%20b.png", "a b.png"),
+      ("fonts/roboto.woff2", "fonts/roboto.woff2"),
+    ] {
+      assert_eq!(normalize_url(url), expected, "{url:?}");
+    }
+  }
+
+  #[test]
+  fn test_is_normalized_url() {
+    let options = NormalizeUrlOptions::default();
+    for url in [
+      "fonts/roboto.woff2",
+      "./img.png?v=1#a",
+      "data:image/png;base64,AAAA",
+    ] {
+      assert!(is_normalized_url(url, options), "{url:?}");
+    }
+    for url in [
+      "",
+      " fonts/roboto.woff2 ",
+      "fonts/roboto\\-1.woff2",
+      "fonts/roboto%201.woff2",
+      "img\\\n.png",
+      "'img.png'",
+    ] {
+      assert!(!is_normalized_url(url, options), "{url:?}");
+    }
+  }
+
+  #[test]
+  fn test_css_unescape() {
+    for (escaped, expected) in [
+      // Hex escapes of 1 to 6 digits, case insensitive
+      (r"\31 23", "123"),
+      (r"\000031", "1"),
+      (r"\0000311", "11"),
+      (r"\4F60\597D", "你好"),
+      (r"\4f60\597d", "你好"),
+      (r"\1F600", "😀"),
+      // A single white space ends the escape and is consumed, `\r\n` counting as one
+      ("\\31 2", "12"),
+      ("\\31\t2", "12"),
+      ("\\31\n2", "12"),
+      ("\\31\r\n2", "12"),
+      ("\\31\x0C2", "12"),
+      ("\\31  2", "1 2"),
+      // Zero, surrogates and code points out of range
+      (r"\0", "\u{FFFD}"),
+      (r"\D800", "\u{FFFD}"),
+      (r"\110000", "\u{FFFD}"),
+      // Any other character escapes itself
+      (r"a\:b", "a:b"),
+      (r"\.\(\)", ".()"),
+      (r"\\", r"\"),
+      (r"\g", "g"),
+      (r"\你", "你"),
+      ("\\ ", " "),
+      // A trailing backslash escapes nothing
+      (r"a\", r"a\"),
+      (r"\\\", r"\\"),
+      (r"\", r"\"),
+    ] {
+      assert_eq!(css_unescape(escaped), expected, "{escaped:?}");
+    }
+    for plain in ["", "foo", "a:b", "你好"] {
+      assert!(matches!(css_unescape(plain), Cow::Borrowed(s) if s == plain));
+    }
+  }
+
+  #[test]
+  fn test_url_to_dependency_request() {
+    let request = |url| url_to_dependency_request(url, Path::new("/app/src"));
+    // Relative
+    assert_eq!(request("./img.png").as_deref(), Some("/app/src/img.png"));
+    assert_eq!(
+      request("../img.png?v=1#a").as_deref(),
+      Some("/app/src/../img.png?v=1#a")
+    );
+    assert_eq!(request("img/a.png").as_deref(), Some("/app/src/img/a.png"));
+    // Packages
+    assert_eq!(request("~pkg/img.png").as_deref(), Some("pkg/img.png"));
+    assert_eq!(
+      request("~@scope/pkg/img.png").as_deref(),
+      Some("@scope/pkg/img.png")
+    );
+    assert_eq!(request("~/img.png").as_deref(), Some("/app/src/~/img.png"));
+    // Absolute
+    for url in [
+      "https://a.com/img.png",
+      "HTTP://a.com/img.png",
+      "//a.com/img.png",
+      "/img.png",
+    ] {
+      assert_eq!(request(url), None, "{url}");
+    }
+    // Data and fragments
+    for url in [
+      "data:image/png;base64,AAAA",
+      "DATA:,a",
+      "#gradient",
+      "?#iefix",
+      "",
+    ] {
+      assert_eq!(request(url), None, "{url:?}");
+    }
+  }
+
+  #[test]
+  fn test_module_request() {
+    assert_eq!(module_request("~pkg/a.png"), Some("pkg/a.png"));
+    assert_eq!(
+      module_request("~@scope/pkg/a.png"),
+      Some("@scope/pkg/a.png")
+    );
+    for request in ["~/a.png", "~", "./~pkg/a.png", "pkg/a.png", "a~b.png", ""] {
+      assert_eq!(module_request(request), None, "{request:?}");
+    }
+  }
+
+  #[test]
+  fn test_replace_auto_public_path() {
+    let css = |urls: &[&str]| {
+      urls
+        .iter()
+        .map(|url| format!("a {{ b: url({url}); }}"))
+        .collect::<String>()
+    };
+    let placeholder = |filename: &str| format!("{AUTO_PUBLIC_PATH_PLACEHOLDER}{filename}");
+    let literal = |public_path: &str| PublicPath::Literal(public_path.to_string());
+    let none = css(&["https://a.com/a.png"]);
+    assert!(matches!(
+      replace_auto_public_path(&none, &literal("/static/"), "main.css"),
+      Cow::Borrowed(_)
+    ));
+    assert!(matches!(
+      replace_auto_public_path(&none, &PublicPath::Runtime, "css/main.css"),
+      Cow::Borrowed(_)
+    ));
+    let one = css(&[&placeholder("a.png")]);
+    assert_eq!(
+      replace_auto_public_path(&one, &literal("/static/"), "main.css"),
+      css(&["/static/a.png"])
+    );
+    assert_eq!(
+      replace_auto_public_path(&one, &literal("/static"), "css/main.css"),
+      css(&["/static/a.png"])
+    );
+    assert_eq!(
+      replace_auto_public_path(&one, &literal(""), "main.css"),
+      css(&["a.png"])
+    );
+    assert_eq!(
+      replace_auto_public_path(&one, &literal("$0/"), "main.css"),
+      css(&["$0/a.png"])
+    );
+    let many = css(&[&placeholder("a.png"), "#a", &placeholder("img/b.png")]);
+    assert_eq!(
+      replace_auto_public_path(&many, &literal("../"), "main.css"),
+      css(&["../a.png", "#a", "../img/b.png"])
+    );
+    // Relative to the stylesheet whatever the public path.
+    let chunk_relative = css(&[&format!("{CHUNK_RELATIVE_PATH_PLACEHOLDER}a.png")]);
+    assert_eq!(
+      replace_auto_public_path(&chunk_relative, &literal("/static/"), "css/main.css"),
+      css(&["../a.png"])
+    );
+  }
+
+  #[test]
+  fn test_public_path_of_output() {
+    assert_eq!(
+      PublicPath::from(&rspack_core::PublicPath::String("/static".to_string())),
+      PublicPath::Literal("/static".to_string())
+    );
+    assert_eq!(
+      PublicPath::from(&rspack_core::PublicPath::Auto),
+      PublicPath::Runtime
+    );
+  }
+
+  #[test]
+  fn test_replace_auto_public_path_runtime() {
+    let css = |url: &str| format!("a {{ b: url({url}); }}");
+    let one = css(&format!("{AUTO_PUBLIC_PATH_PLACEHOLDER}img/a.png"));
+    let runtime = |css_filename: &str| {
+      replace_auto_public_path(&one, &PublicPath::Runtime, css_filename).into_owned()
+    };
+    // Relative to the stylesheet, which sits at the root of the public path.
+    assert_eq!(runtime("main.css"), css("img/a.png"));
+    assert_eq!(runtime("./main.css"), css("img/a.png"));
+    assert_eq!(runtime("css/main.css"), css("../img/a.png"));
+    assert_eq!(runtime("static/css/main.css"), css("../../img/a.png"));
+    assert_eq!(runtime("static/../css/main.css"), css("../img/a.png"));
+  }
+
+  #[test]
+  fn test_css_unescape_invalid_escape() {
+    for (escaped, replaced, kept) in [
+      // Out of range, the white space ending the escape is part of it
+      (r"\110000 ", "\u{FFFD}", r"\110000 "),
+      (r"a\110000 b", "a\u{FFFD}b", r"a\110000 b"),
+      ("a\\110000\r\nb", "a\u{FFFD}b", "a\\110000\r\nb"),
+      (r"\FFFFFF\31", "\u{FFFD}1", r"\FFFFFF1"),
+      // At most 6 digits are the escape, the 7th is a character of its own
+      (r"\1100000", "\u{FFFD}0", r"\1100000"),
+      // Zero and surrogates
+      (r"\0 a", "\u{FFFD}a", r"\0 a"),
+      (r"\D800", "\u{FFFD}", r"\D800"),
+      // Valid escapes are unescaped either way
+      (r"\31 23\:", "123:", "123:"),
+      (r"\10FFFF", "\u{10FFFF}", "\u{10FFFF}"),
+    ] {
+      assert_eq!(
+        css_unescape_with(escaped, InvalidEscape::Replace),
+        replaced,
+        "{escaped:?}"
+      );
+      assert_eq!(css_unescape(escaped), replaced, "{escaped:?}");
+      assert_eq!(
+        css_unescape_with(escaped, InvalidEscape::Keep),
+        kept,
+        "{escaped:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn test_normalize_url_data_uri() {
+    let svg = r#"data:image/svg+xml,%3Csvg xmlns=%22http://www.w3.org/2000/svg%22%3E%3C/svg%3E"#;
+    assert_eq!(normalize_url(&format!(" {svg} ")), svg);
+    assert_eq!(
+      normalize_url(r"DATA:text/plain,a\62 %25"),
+      r"DATA:text/plain,a\62 %25"
+    );
+  }
+
+  #[test]
+  fn test_normalize_url_invalid_escapes() {
+    assert_eq!(normalize_url("foo%20bar%baz.png"), "foo bar%baz.png");
+    assert_eq!(normalize_url("foo%e4%bd%a0%e4.png"), "foo你%e4.png");
+    assert_eq!(normalize_url("100%%ff%zz"), "100%%ff%zz");
+  }
+
+  #[test]
+  fn test_join_class_names() {
+    use ExportValue::*;
+    assert_eq!(
+      join_class_names(&[Static("a".into()), Static("b".into())], " ", false).expect("should join"),
+      r#""a b""#
+    );
+    assert_eq!(
+      join_class_names(
+        &[
+          Static("a".into()),
+          Composed(r#"__webpack_require__("./b.css")["b"]"#.to_string()),
+          Composed(r#"__webpack_require__("./c.css")["c"]"#.to_string()),
+        ],
+        " ",
+        false
+      )
+      .expect("should join"),
+      r#"["a", __webpack_require__("./b.css")["b"], __webpack_require__("./c.css")["c"]].filter(Boolean).join(" ")"#
+    );
+    assert_eq!(
+      join_class_names(
+        &[Static("a".into()), Static("b".into()), Static("c".into())],
+        "|",
+        false
+      )
+      .expect("should join"),
+      r#""a|b|c""#
+    );
+  }
+
+  #[test]
+  fn test_to_js_string_is_html_safe() {
+    for s in [
+      r#""</script><script>alert(1)</script>""#,
+      "</style>",
+      "<!-- a -->",
+      "a>b",
+    ] {
+      let js = to_js_string(s, false).expect("should quote");
+      assert!(!js.contains(['<', '>']), "{js}");
+      assert_eq!(serde_json::from_str::<String>(&js).expect("valid json"), s);
+    }
+    assert_eq!(
+      to_js_string("</script>", false).expect("should quote"),
+      r#""\u003c/script\u003e""#
+    );
+    assert_eq!(
+      to_js_string("a b", false).expect("should quote"),
+      r#""a b""#
+    );
+    assert_eq!(
+      join_class_names(
+        &[
+          ExportValue::Static("</style>".to_string()),
+          ExportValue::Static("b".to_string())
+        ],
+        " ",
+        false
+      )
+      .expect("should join"),
+      r#""\u003c/style\u003e b""#
+    );
+  }
+
+  #[test]
+  fn test_invalid_named_export_warnings() {
+    let mut entries = vec![("foo", &["_foo"][..]), ("foo-bar", &["_foo-bar"][..])];
+    entries.extend(
+      JS_RESERVED_WORDS
+        .iter()
+        .map(|word| (*word, &["_reserved"][..])),
+    );
+    let exports = exports(&entries);
+    let convention = LocalsConvention::from_str("asIs").expect("valid");
+    let warnings = invalid_named_export_warnings(&exports, &convention, Path::new("/a.css"));
+    assert_eq!(warnings.len(), JS_RESERVED_WORDS.len() + 1);
+    let message = &warnings[0].message;
+    assert!(message.contains("/a.css"), "{message}");
+    assert!(message.contains("`foo-bar`"), "{message}");
+    for (word, warning) in JS_RESERVED_WORDS.iter().zip(&warnings[1..]) {
+      assert!(warning.message.contains(&format!("`{word}`")), "{word}");
+    }
+  }
+
+  fn ident(template: &str, filename: &str, local: &str, hasher: &RspackHash) -> String {
+    ident_starting_with(template, filename, local, hasher, IdentStart::Underscore)
+  }
+
+  fn ident_starting_with(
+    template: &str,
+    filename: &str,
+    local: &str,
+    hasher: &RspackHash,
+    ident_start: IdentStart,
+  ) -> String {
+    render_local_ident(
+      filename,
+      local,
+      &LocalIdentName::from(template.to_string()),
+      None,
+      &LocalIdentHashParams {
+        hasher,
+        content_hasher: None,
+        hash_digest: &HashDigest::Hex,
+        hash_digest_length: 20,
+      },
+      ident_start,
+    )
+  }
+
+  #[test]
+  fn test_render_local_ident_templates() {
+    let hasher = RspackHash::new(&HashFunction::Xxhash64);
+    let render = |template, local| ident(template, "src/button/index.module.css", local, &hasher);
+    assert_eq!(render("[local]", "foo"), "foo");
+    assert_eq!(render("[name]__[local]", "foo"), "button__foo");
+    assert_eq!(render("[folder]-[local]", "foo"), "button-foo");
+    assert_eq!(
+      render("[path][name][ext]__[local]", "foo"),
+      "src-button-button-css__foo"
+    );
+    assert_eq!(render("[local]", "1foo"), "_1foo");
+    assert_eq!(render("[hash:base62:6]", "foo").len(), 6);
+    let hash = render("[hash]", "foo");
+    assert_eq!(
+      render("[local]__[hash:8]", "foo"),
+      format!("foo__{}", &hash[..8])
+    );
+    assert_eq!(render("[hash]", "foo"), hash);
+  }
+
+  #[test]
+  fn test_render_local_ident_windows_path() {
+    let hasher = RspackHash::new(&HashFunction::Xxhash64);
+    for template in [
+      "[path][name]__[local]",
+      "[folder]-[local]",
+      "[name]_[hash:8]",
+    ] {
+      assert_eq!(
+        ident(template, "src\\button\\index.module.css", "foo", &hasher),
+        ident(template, "src/button/index.module.css", "foo", &hasher),
+        "{template}"
+      );
+    }
+    assert_eq!(
+      ident(
+        "[path][name]__[local]",
+        "src\\button\\index.module.css",
+        "foo",
+        &hasher
+      ),
+      "src-button-button__foo"
+    );
+  }
+
+  #[test]
+  fn test_render_local_ident_empty_local() {
+    let hasher = RspackHash::new(&HashFunction::Xxhash64);
+    let empty = ident("[local]", "a.css", "", &hasher);
+    assert!(empty.starts_with('_') && empty.len() > 1, "{empty}");
+    assert!(
+      empty[1..].bytes().all(|b| b.is_ascii_alphanumeric()),
+      "{empty}"
+    );
+    // Whatever the template, and stable.
+    for template in ["[local]", "[name]__[local]", "[hash:4]"] {
+      assert_eq!(ident(template, "a.css", "", &hasher), empty, "{template}");
+    }
+    assert_ne!(
+      ident("[local]", "a.css", "", &hasher),
+      ident("[local]", "a.css", "_", &hasher)
+    );
+  }
+
+  #[test]
+  fn test_render_local_ident_hash_never_starts_with_digit() {
+    let hasher = RspackHash::new(&HashFunction::Xxhash64);
+    let hashes: Vec<_> = (0..32)
+      .map(|i| ident("[hash]", "a.css", &format!("local{i}"), &hasher))
+      .collect();
+    assert!(hashes
+      .iter()
+      .all(|hash| !hash.as_bytes()[0].is_ascii_digit()));
+    assert!(hashes.iter().any(|hash| hash.starts_with('_')));
+    // With `identStart: "letter"` digits are remapped to `g`-`p` instead, the length is unchanged.
+    let letter_hashes: Vec<_> = (0..32)
+      .map(|i| {
+        ident_starting_with(
+          "[hash]",
+          "a.css",
+          &format!("local{i}"),
+          &hasher,
+          IdentStart::Letter,
+        )
+      })
+      .collect();
+    assert!(letter_hashes.iter().all(|hash| hash.len() == 16));
+    assert!(letter_hashes
+      .iter()
+      .any(|hash| matches!(hash.as_bytes()[0], b'g'..=b'p')));
+    let base62_hashes: Vec<_> = (0..32)
+      .map(|i| ident("[hash:base62]", "a.css", &format!("local{i}"), &hasher))
+      .collect();
+    assert!(base62_hashes
+      .iter()
+      .all(|hash| hash.as_bytes()[0].is_ascii_alphabetic()));
+  }
+
+  #[test]
+  fn test_render_local_ident_collisions() {
+    let hasher = RspackHash::new(&HashFunction::Xxhash64);
+    let mut other = hasher.clone();
+    "b.css".hash(&mut other);
+    for template in ["[hash]", "[hash:base62]"] {
+      assert_ne!(
+        ident(template, "a.css", "foo", &hasher),
+        ident(template, "a.css", "bar", &hasher)
+      );
+      assert_ne!(
+        ident(template, "a.css", "foo", &hasher),
+        ident(template, "a.css", "foo", &other)
+      );
+    }
+    // Without a hash only the template tells modules apart.
+    assert_eq!(
+      ident("[local]", "a.css", "foo", &hasher),
+      ident("[local]", "b.css", "foo", &other)
+    );
+  }
+
+  #[test]
+  fn test_render_local_ident_content_hash() {
+    let seeded = |data: &str| {
+      let mut hasher = RspackHash::new(&HashFunction::Xxhash64);
+      data.hash(&mut hasher);
+      hasher
+    };
+    let by_content = seeded(".foo { color: red; }");
+    let render = |template: &str, filename: &str, content_hasher| {
+      render_local_ident(
+        filename,
+        "foo",
+        &LocalIdentName::from(template.to_string()),
+        None,
+        &LocalIdentHashParams {
+          hasher: &seeded(filename),
+          content_hasher,
+          hash_digest: &HashDigest::Hex,
+          hash_digest_length: 20,
+        },
+        IdentStart::Underscore,
+      )
+    };
+    let content_hash = render("[contenthash]", "a.css", Some(&by_content));
+    assert_eq!(
+      render("[contenthash]", "b.css", Some(&by_content)),
+      content_hash
+    );
+    assert_ne!(
+      render("[hash]", "a.css", Some(&by_content)),
+      render("[hash]", "b.css", Some(&by_content))
+    );
+    assert_ne!(render("[hash]", "a.css", Some(&by_content)), content_hash);
+    assert_eq!(
+      render("[local]__[contenthash:8]", "a.css", Some(&by_content)),
+      format!("foo__{}", &content_hash[..8])
+    );
+    assert_ne!(
+      render(
+        "[contenthash]",
+        "a.css",
+        Some(&seeded(".foo { color: blue; }"))
+      ),
+      content_hash
+    );
+  }
+
+  fn modules_config(es_module: bool, named_export: bool, convention: &str) -> ModulesConfig {
+    ModulesConfig {
+      locals_convention: LocalsConvention::from_str(convention).expect("valid"),
+      local_ident_name: LocalIdentName::from("[local]".to_string()),
+      local_ident_regexp: None,
+      hash_strategy: Default::default(),
+      hash_prefix: None,
+      prefix: None,
+      local_ident_max_length: None,
+      hash_function: None,
+      hash_digest: None,
+      hash_digest_length: None,
+      hash_salt: None,
+      exports_only: false,
+      exports_type: match (es_module, named_export) {
+        (_, true) => ExportsType::Namespace,
+        (true, false) => ExportsType::Default,
+        (false, false) => ExportsType::CommonJs,
+      },
+      named_export,
+      export_globals: false,
+      exports_by_convention: false,
+      composes_separator: " ".to_string(),
+      export_class_names: ExportClassNames::String,
+      lazy_composes: false,
+      sort_exports: false,
+      pathinfo: false,
+      single_quote: false,
+      freeze_exports: false,
+      resilient: false,
+      line_ending: LineEnding::Lf,
+      trailing_newline: true,
+      detect_ident_collisions: false,
+      pure: false,
+      ident_manifest: None,
+      export_content_hash: false,
+      hash_package_name: false,
+      ident_start: IdentStart::Underscore,
+      invalid_escape: InvalidEscape::Replace,
+      local_scope: LocalScope::Class,
+      local_ident_fn: None,
+    }
+  }
+
+  /// Records every write separately, like a sink that streams elsewhere.
+  #[derive(Default)]
+  struct ChunkedSink(Vec<String>);
+
+  impl Write for ChunkedSink {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+      self.0.push(s.to_string());
+      Ok(())
+    }
+  }
+
+  /// Fails the first write going past `limit` bytes, like a sink streaming elsewhere breaking.
+  struct FailingSink {
+    written: String,
+    limit: usize,
+  }
+
+  impl Write for FailingSink {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+      if self.written.len() + s.len() > self.limit {
+        return Err(std::fmt::Error);
+      }
+      self.written.push_str(s);
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn test_write_exports_error_identifies_failing_write() {
+    let exports = exports(&[("a", &["_a"]), ("b", &["_b"]), ("c-d", &["_c-d"])]);
+    let modules = modules_config(true, false, "asIs");
+    let write = |mut sink: &mut dyn Write| {
+      write_exports(
+        &mut sink,
+        &exports,
+        &modules,
+        Some("./a.css"),
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+    };
+    let mut code = String::new();
+    write(&mut code).expect("should write");
+    let error_at = |limit: usize| {
+      let mut sink = FailingSink {
+        written: String::new(),
+        limit,
+      };
+      let error = write(&mut sink).expect_err("should fail").to_string();
+      // What was written until then is left in the sink, cut before the failing write.
+      assert!(code.starts_with(&sink.written), "{}", sink.written);
+      error
+    };
+    let offset = |line: &str| {
+      code
+        .find(line)
+        .unwrap_or_else(|| panic!("{line} in {code}"))
+    };
+    for (limit, part) in [
+      (0, "the pathinfo comment"),
+      (offset("__webpack_require__.r"), "the start of the exports"),
+      (offset("  \"a\": "), "the export \"a\" (1 of 3)"),
+      (offset("  \"b\": "), "the export \"b\" (2 of 3)"),
+      (offset("  \"c-d\": "), "the export \"c-d\" (3 of 3)"),
+      (offset("};"), "the end of the default export"),
+      (offset("  \"b\": function"), "the named export \"b\""),
+      (code.len() - 1, "the end of the named exports"),
+    ] {
+      let error = error_at(limit);
+      assert!(
+        error.contains(&format!("Failed to write {part} of the css module exports")),
+        "{limit}: {error}"
+      );
+    }
+  }
+
+  #[test]
+  fn test_write_exports_into_custom_sink() {
+    let mut exports = exports(&[("foo", &["_foo"]), ("bar-baz", &["_bar-baz"])]);
+    exports[0].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    for (es_module, named_export, convention) in [
+      (false, false, "asIs"),
+      (true, false, "asIs"),
+      (true, true, "camelCaseOnly"),
+    ] {
+      let modules = modules_config(es_module, named_export, convention);
+      let write = |mut sink: &mut dyn Write| {
+        let mut runtime_requirements = RuntimeGlobals::default();
+        write_exports(
+          &mut sink,
+          &exports,
+          &modules,
+          None,
+          &mut runtime_requirements,
+          None,
+          |from| Ok(format!("{from}?id")),
+        )
+        .expect("should write");
+        runtime_requirements
+      };
+      let mut code = String::new();
+      let expected_runtime_requirements = write(&mut code);
+      let mut sink = ChunkedSink::default();
+      assert_eq!(write(&mut sink), expected_runtime_requirements);
+      assert_eq!(sink.0.concat(), code);
+      assert!(
+        code.contains(r#"__webpack_require__("./a.css?id")"#),
+        "{code}"
+      );
+      // One write per line at most, nothing buffers the whole module.
+      assert!(sink.0.len() >= code.lines().count(), "{:?}", sink.0);
+    }
+  }
+
+  #[test]
+  fn test_composes_from_global() {
+    // `composes: shared from global;`
+    let mut exports = exports(&[("foo", &["_foo"])]);
+    exports[0].push(CssClassName::Global {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "shared".into(),
+        raw: None,
+      },
+    });
+    for es_module in [false, true] {
+      let mut code = String::new();
+      let mut runtime_requirements = RuntimeGlobals::default();
+      write_exports(
+        &mut code,
+        &exports,
+        &modules_config(es_module, false, "asIs"),
+        None,
+        &mut runtime_requirements,
+        None,
+        |from| panic!("global classes have no module to resolve, got {from}"),
+      )
+      .expect("should write");
+      assert!(code.contains(r#"  "foo": "_foo shared","#), "{code}");
+      assert!(
+        !code.contains(&format!("{}(", RuntimeGlobals::REQUIRE)),
+        "{code}"
+      );
+    }
+  }
+
+  #[test]
+  fn test_write_exports_local_and_composed_order() {
+    let source = r#"
+.a { color: red; }
+.primary { composes: b from "./b.css"; composes: a; composes: c d from "./b.css"; }
+.primary:hover { color: blue; }
+"#;
+    let modules = modules_config(false, false, "asIs");
+    let write = || {
+      let mut ss = SWC_COMPILER
+        .parse_file(
+          Default::default(),
+          "index.css",
+          source.to_string(),
+          ParserConfig {
+            css_modules: true,
+            ..Default::default()
+          },
+        )
+        .expect("should parse")
+        .inner;
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new("index.css"),
+        source,
+        &modules,
+        hash_options(),
+      );
+      let exports: IndexMap<JsWord, _> = compile(&mut ss, config).renamed.into_iter().collect();
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    let code = write();
+    // The class of the key first, then the composed ones in declaration order, imported or not.
+    assert!(
+      code.contains(
+        r#"  "primary": ["primary", __webpack_require__("./b.css")["b"], "a", __webpack_require__("./b.css")["c"], __webpack_require__("./b.css")["d"]].filter(Boolean).join(" "),"#
+      ),
+      "{code}"
+    );
+    for _ in 0..10 {
+      assert_eq!(write(), code);
+    }
+  }
+
+  #[test]
+  fn test_write_exports_exports_type() {
+    let mut exports = exports(&[("foo-bar", &["_foo-bar"]), ("zoo", &["_zoo"])]);
+    exports[1].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    let write = |exports_type| {
+      let mut code = String::new();
+      let mut runtime_requirements = RuntimeGlobals::default();
+      write_exports(
+        &mut code,
+        &exports,
+        &ModulesConfig {
+          exports_type,
+          ..modules_config(false, false, "asIs")
+        },
+        None,
+        &mut runtime_requirements,
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      (code, runtime_requirements)
+    };
+    let (code, runtime_requirements) = write(ExportsType::CommonJs);
+    assert!(code.starts_with("module.exports = {\n"), "{code}");
+    assert!(
+      code.contains(r#"__webpack_require__("./a.css")["a"]"#),
+      "{code}"
+    );
+    assert!(runtime_requirements.is_empty());
+
+    // `import styles from` reads the locals from the default export, `import * as styles` the
+    // valid identifiers too.
+    // The composed module is imported once, its classes are read off its namespace.
+    let (code, runtime_requirements) = write(ExportsType::Default);
+    assert!(
+      code.starts_with(
+        r#"__webpack_require__.r(exports);
+var __WEBPACK_IMPORTED_MODULE_0__ = __webpack_require__("./a.css");
+var __WEBPACK_DEFAULT_EXPORT__ = {
+"#
+      ),
+      "{code}"
+    );
+    assert!(
+      code.contains(r#"__WEBPACK_IMPORTED_MODULE_0__["default"]["a"]"#),
+      "{code}"
+    );
+    assert_eq!(code.matches("__webpack_require__(").count(), 1, "{code}");
+    assert!(code.contains(r#"  "zoo": function () { return __WEBPACK_DEFAULT_EXPORT__["zoo"]; },"#));
+    assert!(!code.contains(r#"  "foo-bar": function"#), "{code}");
+    assert!(code.contains(r#"  "default": function () { return __WEBPACK_DEFAULT_EXPORT__; },"#));
+    assert!(runtime_requirements.contains(RuntimeGlobals::DEFINE_PROPERTY_GETTERS));
+
+    // `import * as styles` reads every key, whether it's an identifier or not, there's no default.
+    let (code, runtime_requirements) = write(ExportsType::Namespace);
+    assert_eq!(
+      code,
+      r#"__webpack_require__.r(exports);
+var __WEBPACK_IMPORTED_MODULE_0__ = __webpack_require__("./a.css");
+__webpack_require__.d(exports, {
+  "foo-bar": function () { return "_foo-bar"; },
+  "zoo": function () { return ["_zoo", __WEBPACK_IMPORTED_MODULE_0__["a"]].filter(Boolean).join(" "); },
+});
+"#
+    );
+    assert!(runtime_requirements.contains(RuntimeGlobals::MAKE_NAMESPACE_OBJECT));
+  }
+
+  #[test]
+  fn test_transform_counters() {
+    let source = r#"
+.a { background: url(a.png); }
+.b { composes: a; background: url("./b.png"); }
+.c { composes: x y from "./x.css"; background: url( c%20d.png ); }
+"#;
+    let mut ss = SWC_COMPILER
+      .parse_file(
+        Default::default(),
+        "index.css",
+        source.to_string(),
+        ParserConfig {
+          css_modules: true,
+          ..Default::default()
+        },
+      )
+      .expect("should parse")
+      .inner;
+    let modules = modules_config(false, false, "asIs");
+    let counters = TransformCounters::default();
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("index.css"),
+      source,
+      &modules,
+      hash_options(),
+    )
+    .counters(Some(&counters));
+    let exports: IndexMap<JsWord, _> = compile(&mut ss, config).renamed.into_iter().collect();
+    counters.count_composed_imports(&exports);
+    crate::visitors::analyze_dependencies(
+      &mut ss,
+      Path::new("/app/src"),
+      &mut vec![],
+      &mut vec![],
+      false,
+      Default::default(),
+      Some(&counters),
+    );
+    assert_eq!(
+      counters.snapshot(),
+      TransformCountersSnapshot {
+        new_name_for: 3,
+        // `url("./b.png")` is unquoted by the normalization, so the white space within is kept.
+        fast_urls: 1,
+        slow_urls: 2,
+        composed_imports: 2,
+      }
+    );
+    counters.reset();
+    assert_eq!(counters.snapshot(), TransformCountersSnapshot::default());
+
+    // Without counters the transform renames the same, nothing to count into.
+    let mut ss = SWC_COMPILER
+      .parse_file(
+        Default::default(),
+        "index.css",
+        source.to_string(),
+        ParserConfig {
+          css_modules: true,
+          ..Default::default()
+        },
+      )
+      .expect("should parse")
+      .inner;
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("index.css"),
+      source,
+      &modules,
+      hash_options(),
+    );
+    assert_eq!(compile(&mut ss, config).renamed.len(), 3);
+  }
+
+  #[test]
+  fn test_write_exports_without_exports() {
+    for (es_module, named_export, convention) in [
+      (false, false, "asIs"),
+      (true, false, "asIs"),
+      (true, true, "camelCaseOnly"),
+    ] {
+      let mut code = String::new();
+      let mut runtime_requirements = RuntimeGlobals::default();
+      write_exports(
+        &mut code,
+        &IndexMap::default(),
+        &modules_config(es_module, named_export, convention),
+        None,
+        &mut runtime_requirements,
+        None,
+        |from| panic!("nothing to resolve, got {from}"),
+      )
+      .expect("should write");
+      assert_eq!(code, "");
+      assert!(runtime_requirements.is_empty());
+    }
+  }
+
+  #[test]
+  fn test_clamp_hash_digest_length() {
+    let clamp =
+      |length| clamp_hash_digest_length(&HashFunction::Xxhash64, &HashDigest::Hex, length);
+    assert_eq!(clamp(8), (8, false));
+    assert_eq!(clamp(16), (16, false));
+    // Longer than a sha256 hex digest, let alone the 16 characters of xxhash64.
+    assert_eq!(clamp(100), (16, true));
+
+    let hasher = RspackHash::new(&HashFunction::Xxhash64);
+    let render = |hash_digest_length| {
+      render_local_ident(
+        "a.css",
+        "foo",
+        &LocalIdentName::from("[hash]".to_string()),
+        None,
+        &LocalIdentHashParams {
+          hasher: &hasher,
+          content_hasher: None,
+          hash_digest: &HashDigest::Hex,
+          hash_digest_length,
+        },
+        IdentStart::Underscore,
+      )
+    };
+    assert_eq!(render(100), render(16));
+    // Besides the `_` a leading digit is prefixed with.
+    assert_eq!(render(100).trim_start_matches('_').len(), 16);
+  }
+
+  #[test]
+  fn test_zero_hash_digest_length() {
+    let clamp =
+      |length| clamp_hash_digest_length(&HashFunction::Xxhash64, &HashDigest::Hex, length);
+    assert_eq!(clamp(0), (1, true));
+    assert_eq!(clamp(1), (1, false));
+  }
+
+  #[test]
+  fn test_write_exports_resilient() {
+    let mut exports = exports(&[("foo", &["_foo"]), ("bar", &["_bar"])]);
+    for from in ["./a.css", "./missing.css"] {
+      exports[1].push(CssClassName::Import {
+        name: Ident {
+          span: DUMMY_SP,
+          value: "a".into(),
+          raw: None,
+        },
+        from: from.into(),
+      });
+    }
+    let write = |resilient| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &ModulesConfig {
+          resilient,
+          ..modules_config(false, false, "asIs")
+        },
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| match from {
+          "./missing.css" => Err(internal_error!("Failed to resolve `{from}`")),
+          _ => Ok(from.to_string()),
+        },
+      )
+      .map(|_| code)
+    };
+    assert!(write(false).is_err());
+    let code = write(true).expect("should write");
+    assert!(code.contains("  \"foo\": \"_foo\",\n"), "{code}");
+    // The classes of resolved modules are still composed.
+    assert!(
+      code.contains(
+        "  \"bar\": [\"_bar\", __webpack_require__(\"./a.css\")[\"a\"]].filter(Boolean).join(\" \"),\n"
+      ),
+      "{code}"
+    );
+    assert!(!code.contains("missing"), "{code}");
+  }
+
+  #[test]
+  fn test_write_exports_class_names_array() {
+    let mut exports = exports(&[
+      ("foo", &["_foo"]),
+      ("bar", &["_bar"]),
+      ("baz", &["_baz", "_foo"]),
+    ]);
+    exports[1].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    // An `:export` value.
+    exports.insert(
+      "color".into(),
+      vec![CssClassName::Global {
+        name: Ident {
+          span: DUMMY_SP,
+          value: "#fff".into(),
+          raw: None,
+        },
+      }],
+    );
+    let write = |export_class_names| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &ModulesConfig {
+          export_class_names,
+          ..modules_config(false, false, "asIs")
+        },
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    let composed =
+      r#"  "bar": [].concat("_bar", __webpack_require__("./a.css")["a"]).filter(Boolean),"#;
+    let code = write(ExportClassNames::Array);
+    assert!(code.contains(composed), "{code}");
+    assert!(
+      code.contains("  \"baz\": [\"_baz\", \"_foo\"],\n"),
+      "{code}"
+    );
+    assert!(code.contains("  \"foo\": \"_foo\",\n"), "{code}");
+    assert!(code.contains("  \"color\": \"#fff\",\n"), "{code}");
+    let code = write(ExportClassNames::AlwaysArray);
+    assert!(code.contains(composed), "{code}");
+    assert!(code.contains("  \"foo\": [\"_foo\"],\n"), "{code}");
+    assert!(code.contains("  \"color\": \"#fff\",\n"), "{code}");
+    let code = write(ExportClassNames::String);
+    assert!(code.contains("  \"baz\": \"_baz _foo\",\n"), "{code}");
+  }
+
+  #[test]
+  fn test_write_exports_lazy_composes() {
+    let mut exports = exports(&[("foo", &["_foo"]), ("bar", &["_bar"])]);
+    exports[1].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    let write = |es_module, lazy_composes| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &ModulesConfig {
+          lazy_composes,
+          ..modules_config(es_module, false, "asIs")
+        },
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    for es_module in [false, true] {
+      let code = write(es_module, true);
+      // Only the composing local becomes a getter, in place so the key order is unchanged.
+      assert!(
+        code.contains("  \"foo\": \"_foo\",\n  get \"bar\"() { return ["),
+        "{code}"
+      );
+      assert_eq!(
+        code.lines().count(),
+        write(es_module, false).lines().count()
+      );
+    }
+    assert!(!write(false, false).contains("get "));
+  }
+
+  #[test]
+  fn test_write_exports_sort_exports() {
+    let exports = exports(&[
+      ("zoo", &["_zoo"]),
+      ("foo-bar", &["_foo-bar"]),
+      ("baz", &["_baz"]),
+    ]);
+    let keys = |convention, sort_exports| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &ModulesConfig {
+          sort_exports,
+          ..modules_config(false, false, convention)
+        },
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+        .lines()
+        .filter_map(|line| line.trim().split_once(':'))
+        .map(|(key, _)| key.trim_matches('"').to_string())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(keys("asIs", false), vec!["zoo", "foo-bar", "baz"]);
+    assert_eq!(keys("asIs", true), vec!["baz", "foo-bar", "zoo"]);
+    assert_eq!(
+      keys("camelCase", true),
+      vec!["baz", "foo-bar", "fooBar", "zoo"]
+    );
+    assert_eq!(keys("camelCaseOnly", true), vec!["baz", "fooBar", "zoo"]);
+  }
+
+  #[test]
+  fn test_write_exports_content_hash() {
+    let source = ".zoo {} .foo-bar {} .__hash__ {}";
+    let modules = |convention, export_content_hash| ModulesConfig {
+      local_ident_name: LocalIdentName::from("[hash]".to_string()),
+      sort_exports: true,
+      export_content_hash,
+      ..modules_config(false, false, convention)
+    };
+    let without = modules("asIs", false);
+    assert_eq!(
+      ModulesTransformConfig::with_hash_options(
+        Path::new("a.css"),
+        source,
+        &without,
+        hash_options()
+      )
+      .content_hash(),
+      None
+    );
+    let content_hash = |filename, modules| {
+      ModulesTransformConfig::with_hash_options(
+        Path::new(filename),
+        source,
+        modules,
+        hash_options(),
+      )
+      .content_hash()
+      .map(ToString::to_string)
+      .expect("should hash the content")
+    };
+    let with = modules("asIs", true);
+    let hash = content_hash("a.css", &with);
+    let mut hasher = RspackHash::with_salt(&HashFunction::Xxhash64, &HashSalt::None);
+    source.hash(&mut hasher);
+    assert_eq!(hash, hasher.digest(&HashDigest::Hex).rendered(8));
+    // Of the content only, the same wherever the file is.
+    assert_eq!(content_hash("b/c.css", &with), hash);
+
+    // Added by the parser, replacing the `__hash__` class.
+    let mut exports = exports(&[
+      ("zoo", &["_zoo"]),
+      ("foo-bar", &["_foo-bar"]),
+      (CONTENT_HASH_EXPORT, &["_hash"]),
+    ]);
+    exports[&JsWord::from(CONTENT_HASH_EXPORT)] = vec![CssClassName::Global {
+      name: Ident {
+        span: DUMMY_SP,
+        value: hash.as_str().into(),
+        raw: None,
+      },
+    }];
+    let code = |modules: &ModulesConfig| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    // Last and as is whatever the locals convention.
+    for convention in ["asIs", "camelCase", "camelCaseOnly", "dashesOnly"] {
+      let code = code(&modules(convention, true));
+      let entries = code.lines().skip(1).collect::<Vec<_>>();
+      assert_eq!(
+        entries[entries.len() - 2],
+        format!("  \"__hash__\": \"{hash}\","),
+        "{convention}: {code}"
+      );
+      assert_eq!(code.matches(hash.as_str()).count(), 1, "{code}");
+      assert!(!code.contains("\"hash\""), "{convention}: {code}");
+    }
+  }
+
+  #[test]
+  fn test_composed_class_names_es_module() {
+    let composed = |es_module, named_export, convention| {
+      composed_class_names(
+        "foo-bar",
+        "./a.css",
+        1,
+        &modules_config(es_module, named_export, convention),
+      )
+      .expect("should render")
+    };
+    let require = RuntimeGlobals::REQUIRE;
+    assert_eq!(
+      composed(false, false, "asIs"),
+      format!(r#"{require}("./a.css")["foo-bar"]"#)
+    );
+    // The export of the composed module is read off its namespace the way the module exports it.
+    for (named_export, convention, expected) in [
+      (
+        false,
+        "asIs",
+        r#"__WEBPACK_IMPORTED_MODULE_1__["default"]["foo-bar"]"#,
+      ),
+      (
+        true,
+        "camelCaseOnly",
+        r#"__WEBPACK_IMPORTED_MODULE_1__["fooBar"]"#,
+      ),
+    ] {
+      let code = composed(true, named_export, convention);
+      assert_eq!(code, expected);
+      assert!(!code.contains(&format!("{require}(")), "{code}");
+    }
+  }
+
+  #[test]
+  fn test_write_exports_crlf() {
+    let mut exports = exports(&[("foo", &["_foo"]), ("bar", &["_bar"])]);
+    exports[1].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    let write = |modules: &ModulesConfig| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        modules,
+        Some("./a.module.css"),
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    for (es_module, named_export, lazy_composes) in [
+      (false, false, false),
+      (false, false, true),
+      (true, false, false),
+      (true, true, false),
+    ] {
+      let lf = modules_config(es_module, named_export, "camelCaseOnly");
+      let crlf = ModulesConfig {
+        lazy_composes,
+        line_ending: LineEnding::Crlf,
+        ..lf.clone()
+      };
+      let code = write(&crlf);
+      assert!(code.ends_with("\r\n"), "{code:?}");
+      assert!(!code.replace("\r\n", "").contains('\n'), "{code:?}");
+      assert_eq!(
+        code.replace("\r\n", "\n"),
+        write(&ModulesConfig {
+          lazy_composes,
+          ..lf
+        })
+      );
+
+      // `\r\n` is a single line terminator of js, CRLF output parses like the LF one.
+      let cm: Arc<SourceMap> = Default::default();
+      let fm = cm.new_source_file(FileName::Anon, code.clone());
+      let mut parser = Parser::new(Default::default(), StringInput::from(&*fm), None);
+      assert!(parser.parse_script().is_ok(), "{code}");
+      assert!(parser.take_errors().is_empty(), "{code}");
+    }
+  }
+
+  #[test]
+  fn test_write_exports_trailing_newline() {
+    let exports = exports(&[("foo", &["_foo"]), ("bar", &["_bar"])]);
+    let write = |modules: &ModulesConfig| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    for (es_module, named_export) in [(false, false), (true, false), (true, true)] {
+      for (line_ending, eol) in [(LineEnding::Lf, "\n"), (LineEnding::Crlf, "\r\n")] {
+        let modules = ModulesConfig {
+          line_ending,
+          ..modules_config(es_module, named_export, "camelCaseOnly")
+        };
+        let with_newline = write(&modules);
+        assert!(
+          with_newline.ends_with(&format!(";{eol}")),
+          "{with_newline:?}"
+        );
+        let without_newline = write(&ModulesConfig {
+          trailing_newline: false,
+          ..modules
+        });
+        assert!(without_newline.ends_with(';'), "{without_newline:?}");
+        assert_eq!(format!("{without_newline}{eol}"), with_newline);
+      }
+    }
+  }
+
+  /// The keys of the object literals passed to `Object.freeze` by the top level statements of the
+  /// script `code`, either assigned or declared.
+  fn frozen_object_keys(code: &str) -> Vec<Vec<String>> {
+    use swc_core::ecma::ast::{Callee, Decl, Expr, MemberProp, Prop, PropName, PropOrSpread, Stmt};
+    let cm: Arc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(FileName::Anon, code.to_string());
+    let script = Parser::new(Default::default(), StringInput::from(&*fm), None)
+      .parse_script()
+      .expect("should parse");
+    script
+      .body
+      .iter()
+      .filter_map(|stmt| match stmt {
+        Stmt::Expr(stmt) => stmt.expr.as_assign().map(|assign| &*assign.right),
+        Stmt::Decl(Decl::Var(var)) => var.decls[0].init.as_deref(),
+        _ => None,
+      })
+      .filter_map(|expr| {
+        let call = expr.as_call()?;
+        let Callee::Expr(callee) = &call.callee else {
+          return None;
+        };
+        let callee = callee.as_member()?;
+        let is_freeze = matches!(&*callee.obj, Expr::Ident(obj) if &*obj.sym == "Object")
+          && matches!(&callee.prop, MemberProp::Ident(prop) if &*prop.sym == "freeze");
+        let object = call.args.first().filter(|_| is_freeze)?.expr.as_object()?;
+        let keys = object.props.iter().filter_map(|prop| {
+          let key = match prop {
+            PropOrSpread::Prop(prop) => match &**prop {
+              Prop::KeyValue(prop) => &prop.key,
+              Prop::Getter(prop) => &prop.key,
+              _ => return None,
+            },
+            PropOrSpread::Spread(_) => return None,
+          };
+          match key {
+            PropName::Str(key) => Some(key.value.to_string()),
+            _ => None,
+          }
+        });
+        Some(keys.collect())
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_write_exports_freeze_exports() {
+    let mut exports = exports(&[("foo", &["_foo"]), ("bar", &["_bar"])]);
+    exports[1].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    let write = |modules: &ModulesConfig| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    for (es_module, lazy_composes) in [(false, false), (false, true), (true, false)] {
+      let modules = ModulesConfig {
+        lazy_composes,
+        ..modules_config(es_module, false, "asIs")
+      };
+      let code = write(&ModulesConfig {
+        freeze_exports: true,
+        ..modules.clone()
+      });
+      assert!(code.contains(" = Object.freeze({\n"), "{code}");
+      assert!(code.contains("\n});\n"), "{code}");
+      assert_eq!(
+        frozen_object_keys(&code),
+        vec![vec!["foo".to_string(), "bar".to_string()]],
+        "{code}"
+      );
+      // Off by default.
+      let code = write(&modules);
+      assert!(!code.contains("Object.freeze("), "{code}");
+      assert!(frozen_object_keys(&code).is_empty(), "{code}");
+    }
+    // Named exports are getters of the namespace object, there's no object to freeze.
+    let modules = modules_config(true, true, "camelCaseOnly");
+    assert_eq!(
+      write(&ModulesConfig {
+        freeze_exports: true,
+        ..modules.clone()
+      }),
+      write(&modules)
+    );
+  }
+
+  #[test]
+  fn test_write_exports_single_quote() {
+    let exports = exports(&[("it's", &[r#"a"b'c\d"#]), ("plain", &["_plain"])]);
+    for es_module in [false, true] {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &ModulesConfig {
+          single_quote: true,
+          ..modules_config(es_module, false, "asIs")
+        },
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      assert!(code.contains(r#"  'it\'s': 'a"b\'c\\d',"#), "{code}");
+      assert!(code.contains("  'plain': '_plain',\n"), "{code}");
+      if es_module {
+        assert!(code.contains("  'default': function ()"), "{code}");
+      }
+    }
+    for s in ["", "a", r#"'"'"#, r"\'", "</style>", "\n\u{2028}"] {
+      let quoted = to_js_string(s, true).expect("should quote");
+      assert!(
+        quoted.starts_with('\'') && quoted.ends_with('\''),
+        "{quoted}"
+      );
+      // Back to JSON, which is a js string literal of the same value.
+      let json = format!(
+        "\"{}\"",
+        quoted[1..quoted.len() - 1]
+          .replace(r"\'", "'")
+          .replace('"', r#"\""#)
+      );
+      assert_eq!(
+        serde_json::from_str::<String>(&json).expect("valid json"),
+        s
+      );
+    }
+  }
+
+  fn parse_module(source: &str) -> Stylesheet {
+    SWC_COMPILER
+      .parse_file(
+        Default::default(),
+        "index.css",
+        source.to_string(),
+        ParserConfig {
+          css_modules: true,
+          ..Default::default()
+        },
+      )
+      .expect("should parse")
+      .inner
+  }
+
+  fn hash_options() -> LocalIdentHashOptions<'static> {
+    LocalIdentHashOptions {
+      hash_function: &HashFunction::Xxhash64,
+      hash_digest: &HashDigest::Hex,
+      hash_digest_length: 8,
+      hash_salt: &HashSalt::None,
+    }
+  }
+
+  #[test]
+  fn test_local_ident_max_length() {
+    use swc_core::css::modules::TransformConfig;
+
+    let long = "a".repeat(300);
+    let locals = [
+      format!("{long}1"),
+      format!("{long}2"),
+      format!("{long}-3"),
+      "short".to_string(),
+    ];
+    let render = |local_ident_name: &str, local_ident_max_length| {
+      let modules = ModulesConfig {
+        local_ident_name: LocalIdentName::from(local_ident_name.to_string()),
+        local_ident_max_length,
+        ..modules_config(false, false, "asIs")
+      };
+      let hashes = RefCell::new(IndexMap::default());
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new("src/components/button/index.module.css"),
+        "",
+        &modules,
+        hash_options(),
+      )
+      .record_hashes(&hashes);
+      let idents = locals
+        .iter()
+        .map(|local| config.new_name_for(&local.as_str().into()).to_string())
+        .collect::<Vec<_>>();
+      // In the order of `locals`.
+      (
+        idents,
+        hashes.into_inner().into_values().collect::<Vec<_>>(),
+      )
+    };
+    for (local_ident_name, hash_length) in [
+      ("[folder]__[name]__[local]--[hash]", usize::MAX),
+      ("[hash]__[local]", usize::MAX),
+      ("[local]", usize::MAX),
+      ("[name]__[local]__[hash:6]", 6),
+    ] {
+      let (idents, hashes) = render(local_ident_name, Some(40));
+      let (untruncated, _) = render(local_ident_name, None);
+      for ((ident, untruncated), hash) in idents.iter().zip(&untruncated).zip(&hashes) {
+        assert!(ident.chars().count() <= 40, "{local_ident_name}: {ident}");
+        assert_eq!(escape_local_ident(ident, IdentStart::Underscore), *ident);
+        if ident != untruncated {
+          let hash = &hash[..hash.len().min(hash_length)];
+          assert!(ident.contains(hash), "{local_ident_name}: {ident}");
+        }
+      }
+      // Only the idents that are too long are cut.
+      assert_eq!(idents[3], untruncated[3]);
+      assert_ne!(idents[0], untruncated[0]);
+      assert_eq!(
+        idents.iter().collect::<rustc_hash::FxHashSet<_>>().len(),
+        locals.len(),
+        "{local_ident_name}: {idents:?}"
+      );
+      // The same on every build.
+      assert_eq!(idents, render(local_ident_name, Some(40)).0);
+    }
+    let (idents, hashes) = render("[folder]__[name]__[local]--[hash]", Some(40));
+    let hash = &hashes[0];
+    let head = "button__button__aaaaaaaaaaaaaaaaaaaaaaaa";
+    assert_eq!(idents[0], format!("{}{hash}", &head[..40 - hash.len()]));
+    let (idents, hashes) = render("[local]", Some(40));
+    let hash = &hashes[0];
+    assert_eq!(idents[0], format!("{}_{hash}", "a".repeat(39 - hash.len())));
+    // The hash is never cut, even when it's longer on its own.
+    let (idents, hashes) = render("[hash]", Some(4));
+    assert_eq!(idents[0], hashes[0]);
+
+    // Whatever the one character hash is, `[local]` has it too, only the hash itself is kept.
+    let local = "abcdefghijklmnop0123456789".repeat(3);
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[local]_[hash:1]".to_string()),
+      local_ident_max_length: Some(10),
+      ..modules_config(false, false, "asIs")
+    };
+    let hashes = RefCell::new(IndexMap::default());
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("src/components/button/index.module.css"),
+      "",
+      &modules,
+      hash_options(),
+    )
+    .record_hashes(&hashes);
+    let ident = config.new_name_for(&local.as_str().into());
+    let hash = hashes
+      .into_inner()
+      .into_values()
+      .next()
+      .expect("should record the hash");
+    assert_eq!(&*ident, format!("{}{}", &local[..9], &hash[..1]));
+  }
+
+  #[test]
+  fn test_letter_ident_start() {
+    let source = r".foo {} .\31 23 {} .-\32 {} .bar { composes: foo; }";
+    let local_ident_fn: LocalIdentFn = Arc::new(|context| Some(format!("1{}", context.local)));
+    let idents = |ident_start, local_ident_name: &str, local_ident_fn, prefix: Option<&str>| {
+      let modules = ModulesConfig {
+        local_ident_name: LocalIdentName::from(local_ident_name.to_string()),
+        local_ident_fn,
+        prefix: prefix.map(ToString::to_string),
+        ident_start,
+        ..modules_config(false, false, "asIs")
+      };
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new("src/1/2.css"),
+        source,
+        &modules,
+        hash_options(),
+      );
+      let result = compile(&mut parse_module(source), config);
+      result
+        .renamed
+        .into_values()
+        .map(|names| match &names[0] {
+          CssClassName::Local { name } => name.value.to_string(),
+          _ => unreachable!(),
+        })
+        .collect::<Vec<_>>()
+    };
+    let mut underscored = 0;
+    for (local_ident_name, local_ident_fn, prefix) in [
+      ("[local]", None, None),
+      ("[hash]", None, None),
+      ("[name]__[local]", None, None),
+      ("[folder]-[local]", None, None),
+      ("[local]", Some(local_ident_fn), None),
+      ("[local]", None, Some("-")),
+    ] {
+      for name in idents(
+        IdentStart::Letter,
+        local_ident_name,
+        local_ident_fn.clone(),
+        prefix,
+      ) {
+        assert!(!name.starts_with('_'), "{local_ident_name}: {name}");
+        assert_eq!(escape_local_ident(&name, IdentStart::Underscore), name);
+      }
+      underscored += idents(
+        IdentStart::Underscore,
+        local_ident_name,
+        local_ident_fn.clone(),
+        prefix,
+      )
+      .iter()
+      .filter(|name| name.starts_with('_'))
+      .count();
+    }
+    // The same idents are `_` prefixed otherwise.
+    assert!(underscored > 0);
+  }
+
+  #[test]
+  fn test_prefix() {
+    let source = r".foo {} .\31 23 {} .-\32 {} .bar { composes: foo; }";
+    let local_ident_fn: LocalIdentFn = Arc::new(|context| Some(context.local.to_string()));
+    for (local_ident_name, local_ident_fn) in [
+      ("[local]", None),
+      ("[hash]", None),
+      ("[path][name][ext]__[local]", None),
+      ("[local]", Some(local_ident_fn)),
+    ] {
+      for prefix in ["mylib-", "-", "_"] {
+        let modules = ModulesConfig {
+          local_ident_name: LocalIdentName::from(local_ident_name.to_string()),
+          local_ident_fn: local_ident_fn.clone(),
+          prefix: Some(prefix.to_string()),
+          ..modules_config(false, false, "asIs")
+        };
+        let config = ModulesTransformConfig::with_hash_options(
+          Path::new("src/a.css"),
+          source,
+          &modules,
+          hash_options(),
+        );
+        let result = compile(&mut parse_module(source), config);
+        assert_eq!(result.renamed.len(), 4);
+        for names in result.renamed.values() {
+          let CssClassName::Local { name } = &names[0] else {
+            unreachable!()
+          };
+          let name = &*name.value;
+          assert!(name.starts_with(prefix), "{name}");
+          assert_eq!(escape_local_ident(name, IdentStart::Underscore), name);
+          let leading = name.trim_start_matches('-').as_bytes().first();
+          assert!(leading.map_or(true, |b| !b.is_ascii_digit()), "{name}");
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_record_hashes() {
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[local]-[hash]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let source = ".foo {} .bar { composes: foo; } .baz {}";
+    let recorded = RefCell::new(IndexMap::default());
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("a.css"),
+      source,
+      &modules,
+      hash_options(),
+    )
+    .record_hashes(&recorded);
+    let result = compile(&mut parse_module(source), config);
+    let collector = LocalHashCollector::default();
+    let module = ModuleIdentifier::from("a.css");
+    collector.record(module, recorded.into_inner());
+    let recorded = collector.hashes(&module).expect("should be recorded");
+    assert_eq!(
+      recorded.keys().collect::<Vec<_>>(),
+      vec!["foo", "bar", "baz"]
+    );
+    for (local, names) in &result.renamed {
+      let CssClassName::Local { name } = &names[0] else {
+        unreachable!()
+      };
+      assert_eq!(name.value, format!("{local}-{}", recorded[local]));
+    }
+    // Once removed from the compilation, a module is forgotten.
+    collector.retain(|_| false);
+    assert!(collector.hashes(&module).is_none());
+  }
+
+  #[test]
+  fn test_local_ident_collisions() {
+    // 17 locals and 16 one character hex hashes, two of them have to share an ident.
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[hash]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let collector = LocalIdentCollector::default();
+    for (filename, source) in [
+      (
+        "a.css",
+        ".a0 {} .a1 {} .a2 {} .a3 {} .a4 {} .a5 {} .a6 {} .a7 {} .a8 {}",
+      ),
+      (
+        "b.css",
+        ".b0 {} .b1 {} .b2 {} .b3 {} .b4 {} .b5 {} .b6 {} .b7 {}",
+      ),
+    ] {
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new(filename),
+        source,
+        &modules,
+        LocalIdentHashOptions {
+          hash_digest_length: 1,
+          ..hash_options()
+        },
+      );
+      let result = compile(&mut parse_module(source), config);
+      let idents = result
+        .renamed
+        .into_iter()
+        .map(|(local, names)| match &names[0] {
+          CssClassName::Local { name } => (local, name.value.clone()),
+          _ => unreachable!(),
+        })
+        .collect();
+      collector.record(filename.into(), idents);
+    }
+
+    let collisions = collector.collisions(|_| true);
+    assert!(!collisions.is_empty());
+    for (ident, locals) in &collisions {
+      assert_eq!(ident.trim_start_matches('_').len(), 1, "{ident}");
+      assert!(locals.len() > 1, "{locals:?}");
+    }
+    assert!(collector
+      .collisions(|module| module.as_str() == "b.css")
+      .iter()
+      .all(|(_, locals)| locals.iter().all(|(module, _)| module.as_str() == "b.css")));
+
+    // Parsing a module again replaces its idents.
+    collector.record("a.css".into(), vec![]);
+    collector.record("b.css".into(), vec![("b0".into(), "x".into())]);
+    assert!(collector.collisions(|_| true).is_empty());
+  }
+
+  #[test]
+  fn test_local_ident_manifest() {
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[name]__[local]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let collector = LocalIdentCollector::default();
+    for (filename, source) in [
+      ("src/a.css", ".a {} .shared {}"),
+      ("src/b.css", ".shared { composes: a from './a.css'; }"),
+      ("src/removed.css", ".c {}"),
+    ] {
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new(filename),
+        source,
+        &modules,
+        hash_options(),
+      );
+      let result = compile(&mut parse_module(source), config);
+      let idents = result
+        .renamed
+        .into_iter()
+        .map(|(local, names)| match &names[0] {
+          CssClassName::Local { name } => (local, name.value.clone()),
+          _ => unreachable!(),
+        })
+        .collect();
+      collector.record(filename.into(), idents);
+    }
+
+    let manifest = collector
+      .manifest(|module| (module.as_str() != "src/removed.css").then(|| format!("./{module}")));
+    let json = serde_json::to_string(&manifest).expect("should serialize the manifest");
+    assert_eq!(
+      json,
+      r#"{"./src/a.css":{"a":"a__a","shared":"a__shared"},"./src/b.css":{"shared":"b__shared"}}"#
+    );
+
+    // Once removed from the compilation, a module is forgotten.
+    collector.retain(|module| module.as_str() != "src/removed.css");
+    assert_eq!(
+      collector.manifest(|module| Some(module.to_string())).len(),
+      2
+    );
+  }
+
+  /// The exports of the css module `source` named `a.css`, in the order the parser sorts them in.
+  fn compiled_exports(
+    source: &str,
+    modules: &ModulesConfig,
+  ) -> IndexMap<JsWord, Vec<CssClassName>> {
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("a.css"),
+      source,
+      modules,
+      hash_options(),
+    );
+    let mut exports: IndexMap<JsWord, _> = compile(&mut parse_module(source), config)
+      .renamed
+      .into_iter()
+      .collect();
+    exports.sort_keys();
+    exports
+  }
+
+  /// A compilation of nothing, enough to render the exports of a module composing from none.
+  fn empty_compilation() -> Compilation {
+    let (options, _) = serde_json::from_str::<rspack_testing::TestConfig>("{}")
+      .expect("should parse the config")
+      .apply(PathBuf::from("/app"));
+    let options = Arc::new(options);
+    let resolver_factory = Arc::new(rspack_core::ResolverFactory::new(options.resolve.clone()));
+    Compilation::new(
+      options.clone(),
+      Default::default(),
+      Arc::new(rspack_core::PluginDriver::new(
+        options.clone(),
+        vec![],
+        resolver_factory.clone(),
+      )),
+      resolver_factory,
+      Arc::new(rspack_core::cache::Cache::new(options)),
+    )
+  }
+
+  #[test]
+  fn test_exports_cache() {
+    let source = ".a {} .b { composes: a; }";
+    let modules = |local_ident_name: &str| ModulesConfig {
+      local_ident_name: LocalIdentName::from(local_ident_name.to_string()),
+      ..modules_config(true, false, "asIs")
+    };
+    let key = |resource: &str, source: &str, modules: &ModulesConfig| {
+      exports_cache_key(resource, source, &compiled_exports(source, modules))
+    };
+    let hashed = modules("[hash]__[local]");
+    assert_eq!(
+      key("/app/a.css", source, &hashed),
+      key("/app/a.css", source, &modules("[hash]__[local]"))
+    );
+    assert_ne!(
+      key("/app/a.css", source, &hashed),
+      key("/app/b.css", source, &hashed)
+    );
+    assert_ne!(
+      key("/app/a.css", source, &hashed),
+      key("/app/a.css", ".a {}", &hashed)
+    );
+    // The same locals renamed otherwise.
+    assert_ne!(
+      key("/app/a.css", source, &hashed),
+      key("/app/a.css", source, &modules("[local]"))
+    );
+    assert_ne!(
+      key("/app/a.css", source, &hashed),
+      key(
+        "/app/a.css",
+        source,
+        &ModulesConfig {
+          hash_prefix: Some("prefix".to_string()),
+          ..modules("[hash]__[local]")
+        }
+      )
+    );
+
+    let cache = ExportsCache::default();
+    let renders = RefCell::new(0);
+    let module = ModuleIdentifier::from("a.css");
+    let get = |modules: &ModulesConfig| {
+      let exports = compiled_exports(source, modules);
+      let mut runtime_requirements = RuntimeGlobals::default();
+      let code = cache
+        .get_or_render(
+          module,
+          exports_cache_key("/app/a.css", source, &exports),
+          &mut runtime_requirements,
+          |runtime_requirements| {
+            *renders.borrow_mut() += 1;
+            let mut code = String::new();
+            write_exports(
+              &mut code,
+              &exports,
+              modules,
+              None,
+              runtime_requirements,
+              None,
+              |from| Ok(from.to_string()),
+            )?;
+            Ok(code)
+          },
+        )
+        .expect("should render");
+      (code, runtime_requirements)
+    };
+    let (code, runtime_requirements) = get(&hashed);
+    assert_eq!(*renders.borrow(), 1);
+    // Unchanged, the code and runtime requirements are those cached.
+    assert_eq!(get(&hashed), (code.clone(), runtime_requirements));
+    assert!(runtime_requirements.contains(RuntimeGlobals::DEFINE_PROPERTY_GETTERS));
+    assert_eq!(*renders.borrow(), 1);
+    // Another `localIdentName` renders again, and replaces the cached code.
+    let (unhashed, _) = get(&modules("[local]"));
+    assert_eq!(*renders.borrow(), 2);
+    assert_ne!(unhashed, code);
+    assert!(unhashed.contains(r#""b": "b a""#), "{unhashed}");
+    get(&hashed);
+    assert_eq!(*renders.borrow(), 3);
+    // Once removed from the compilation, the module renders again.
+    cache.retain(|_| false);
+    get(&hashed);
+    assert_eq!(*renders.borrow(), 4);
+  }
+
+  #[test]
+  fn test_exports_cache_of_changed_source() {
+    let compilation = empty_compilation();
+    let identifier = ModuleIdentifier::from("/app/a.css");
+    let module = RawModule::new(
+      String::new(),
+      identifier,
+      "./a.css".to_string(),
+      RuntimeGlobals::default(),
+    );
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[local]__[hash]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let cache = ExportsCache::default();
+    let render = |source: &str| {
+      let exports = compiled_exports(source, &modules);
+      let key = exports_cache_key("/app/a.css", source, &exports);
+      let code = css_modules_exports_to_string(
+        &exports,
+        &module,
+        &compilation,
+        &modules,
+        &mut RuntimeGlobals::default(),
+        None,
+        Some((&cache, key)),
+      )
+      .expect("should render");
+      assert_eq!(
+        cache.exports.lock().expect("should lock")[&identifier].1,
+        code
+      );
+      code
+    };
+    let code = render(".a {}");
+    assert!(code.contains(r#""a": "a__"#), "{code}");
+    assert_eq!(render(".a {}"), code);
+    // Changed, the module is rendered again rather than served the code cached for its old source.
+    let changed = render(".a {} .b {}");
+    assert_ne!(changed, code);
+    assert!(changed.contains(r#""b": "b__"#), "{changed}");
+  }
+
+  #[test]
+  fn test_full_hash_digest_length() {
+    assert_eq!(
+      clamp_hash_digest_length(
+        &HashFunction::Xxhash64,
+        &HashDigest::Hex,
+        FULL_HASH_DIGEST_LENGTH
+      ),
+      (16, false)
+    );
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[hash]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let source = ".foo {}";
+    let recorded = RefCell::new(IndexMap::default());
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("a.css"),
+      source,
+      &modules,
+      LocalIdentHashOptions {
+        hash_digest_length: FULL_HASH_DIGEST_LENGTH,
+        ..hash_options()
+      },
+    )
+    .record_hashes(&recorded);
+    let result = compile(&mut parse_module(source), config);
+    let hash = &recorded.borrow()[&JsWord::from("foo")];
+    // The 16 characters of a hex xxhash64 digest.
+    assert_eq!(hash.len(), 16, "{hash}");
+    let CssClassName::Local { name } = &result.renamed[&JsWord::from("foo")][0] else {
+      unreachable!()
+    };
+    assert_eq!(&*name.value, hash.as_str());
+  }
+
+  #[test]
+  fn test_write_exports_escaped_class_names() {
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[local]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let source = r".\31 23 {} .a\:b {} .c { composes: \31 23; }";
+    let mut stylesheet = parse_module(source);
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("a.css"),
+      source,
+      &modules,
+      hash_options(),
+    );
+    let exports = compile(&mut stylesheet, config)
+      .renamed
+      .into_iter()
+      .collect();
+    let mut code = String::new();
+    write_exports(
+      &mut code,
+      &exports,
+      &modules,
+      None,
+      &mut RuntimeGlobals::default(),
+      None,
+      |from| Ok(from.to_string()),
+    )
+    .expect("should write");
+    // Keys are the unescaped class names, values the classes rendered into the stylesheet.
+    assert!(code.contains("  \"123\": \"_123\",\n"), "{code}");
+    assert!(code.contains("  \"a:b\": \"a-b\",\n"), "{code}");
+    assert!(code.contains("  \"c\": \"c _123\",\n"), "{code}");
+    let (css, _) = SWC_COMPILER
+      .codegen(
+        Default::default(),
+        &stylesheet,
+        SwcCssSourceMapGenConfig {
+          enable: false,
+          emit_columns: false,
+          inline_sources_content: false,
+        },
+      )
+      .expect("should codegen");
+    assert!(css.contains("._123") && css.contains(".a-b"), "{css}");
+  }
+
+  #[test]
+  fn test_compound_local_selector() {
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[hash]__[local]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let renamed = |source: &str| {
+      let mut stylesheet = parse_module(source);
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new("a.css"),
+        source,
+        &modules,
+        hash_options(),
+      );
+      let exports: IndexMap<String, _> = compile(&mut stylesheet, config)
+        .renamed
+        .into_iter()
+        .map(|(local, names)| match &names[..] {
+          [CssClassName::Local { name }] => (local.to_string(), name.value.to_string()),
+          _ => panic!("{local} should be renamed once: {names:?}"),
+        })
+        .collect();
+      let (css, _) = SWC_COMPILER
+        .codegen(
+          Default::default(),
+          &stylesheet,
+          SwcCssSourceMapGenConfig {
+            enable: false,
+            emit_columns: false,
+            inline_sources_content: false,
+          },
+        )
+        .expect("should codegen");
+      (exports, css)
+    };
+    let (compound, css) = renamed(":local(.a .b) {} :local(.c.d > .e) {} .f :local(.g) {}");
+    // Hashed like the classes would be on their own.
+    let (separate, _) = renamed(".a {} .b {} .c {} .d {} .e {} .f {} .g {}");
+    for local in ["a", "b", "c", "d", "e", "f", "g"] {
+      let ident = compound
+        .get(local)
+        .unwrap_or_else(|| panic!("{local} should be exported: {compound:?}"));
+      assert_eq!(Some(ident), separate.get(local));
+      assert!(ident.ends_with(&format!("__{local}")), "{ident}");
+    }
+    assert_eq!(compound.len(), 7);
+    let ident = |local: &str| format!(".{}", compound[local]);
+    assert!(
+      css.contains(&format!("{} {}", ident("a"), ident("b"))),
+      "{css}"
+    );
+    assert!(
+      css.contains(&format!("{}{} > {}", ident("c"), ident("d"), ident("e"))),
+      "{css}"
+    );
+  }
+
+  #[test]
+  fn test_code_generation_data_global_classes() {
+    let source = r#"
+.a { color: red; }
+:global(.b) .c, :global .d { color: blue; }
+.e :global(.b.f) { color: green; }
+:local(.g) { composes: h from global; }
+"#;
+    let mut data = rspack_core::CodeGenerationData::default();
+    data.insert(CodeGenerationDataGlobalClasses::new(
+      crate::visitors::collect_global_classes(&parse_module(source)),
+    ));
+    let globals = data
+      .get::<CodeGenerationDataGlobalClasses>()
+      .expect("should have the global classes");
+    // Counted once each, the locals and the classes composed from global aren't.
+    assert_eq!(globals.len(), 3);
+    assert_eq!(globals.inner(), ["b", "d", "f"].map(JsWord::from));
+    assert!(
+      CodeGenerationDataGlobalClasses::new(crate::visitors::collect_global_classes(&parse_module(
+        ".a { composes: b from global; }"
+      )))
+      .is_empty()
+    );
+  }
+
+  #[test]
+  fn test_local_ident_regexp() {
+    let source = ".primary {} .icon {}";
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[1]_[local]__[hash:4]".to_string()),
+      local_ident_regexp: Some(Regex::new(r"([^/]+)/styles\.module\.css$").expect("valid")),
+      ..modules_config(false, false, "asIs")
+    };
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("src\\Button\\styles.module.css"),
+      source,
+      &modules,
+      hash_options(),
+    );
+    let idents = compile(&mut parse_module(source), config)
+      .renamed
+      .into_iter()
+      .map(|(local, names)| match &names[..] {
+        [CssClassName::Local { name }] => (local.to_string(), name.value.to_string()),
+        names => panic!("unexpected {names:?}"),
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(idents.len(), 2);
+    for (local, ident) in &idents {
+      assert!(ident.starts_with(&format!("Button_{local}__")), "{ident}");
+    }
+    // A different regexp renders other idents, the cached exports can't be reused.
+    let other = ModulesConfig {
+      local_ident_regexp: Some(Regex::new(r"([^/]+)\.module\.css$").expect("valid")),
+      ..modules.clone()
+    };
+    let key = |modules: &ModulesConfig| {
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new("src/Button/styles.module.css"),
+        source,
+        modules,
+        hash_options(),
+      );
+      let exports = compile(&mut parse_module(source), config)
+        .renamed
+        .into_iter()
+        .collect();
+      exports_cache_key("src/Button/styles.module.css", source, &exports)
+    };
+    assert_ne!(key(&other), key(&modules));
+  }
+
+  #[test]
+  fn test_hash_package_name() {
+    let source = ".a {}";
+    let ident = |modules: &ModulesConfig, package_name| {
+      let config = ModulesTransformConfig::with_hash_options(
+        Path::new("src/button.css"),
+        source,
+        modules,
+        hash_options(),
+      )
+      .package_name(package_name);
+      match &compile(&mut parse_module(source), config).renamed[&JsWord::from("a")][..] {
+        [CssClassName::Local { name }] => name.value.to_string(),
+        names => panic!("unexpected {names:?}"),
+      }
+    };
+    let without = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[hash]__[local]".to_string()),
+      ..modules_config(false, false, "asIs")
+    };
+    let with = ModulesConfig {
+      hash_package_name: true,
+      ..without.clone()
+    };
+    // The same relative path in two packages.
+    assert_ne!(ident(&with, Some("@app/a")), ident(&with, Some("@app/b")));
+    assert_eq!(ident(&with, Some("@app/a")), ident(&with, Some("@app/a")));
+    assert_eq!(ident(&with, None), ident(&without, None));
+    assert_eq!(
+      ident(&without, Some("@app/a")),
+      ident(&without, Some("@app/b"))
+    );
+    assert_eq!(ident(&without, Some("@app/a")), ident(&without, None));
+  }
+
+  #[test]
+  fn test_attribute_local_scope() {
+    let modules = ModulesConfig {
+      local_ident_name: LocalIdentName::from("[hash]__[local]".to_string()),
+      local_scope: LocalScope::Attribute,
+      ..modules_config(false, false, "asIs")
+    };
+    let source = r#"
+.a .b::before { color: red; }
+.c { composes: a; }
+:global(.a) { color: blue; }
+@keyframes d { from { opacity: 0; } }
+.e { animation: d 1s; }
+"#;
+    let config = |filename| {
+      ModulesTransformConfig::with_hash_options(
+        Path::new(filename),
+        source,
+        &modules,
+        hash_options(),
+      )
+    };
+    let scope_id = config("a.css")
+      .scope_id()
+      .expect("should scope by attribute");
+    assert_eq!(scope_id.len(), "data-v-".len() + 8, "{scope_id}");
+    assert!(scope_id.starts_with("data-v-"), "{scope_id}");
+    // The same for the whole file and on every build, not for other files.
+    assert_eq!(config("a.css").scope_id().as_ref(), Some(&scope_id));
+    assert_ne!(config("b.css").scope_id().as_ref(), Some(&scope_id));
+    let class_modules = ModulesConfig {
+      local_scope: LocalScope::Class,
+      ..modules.clone()
+    };
+    assert_eq!(
+      ModulesTransformConfig::with_hash_options(
+        Path::new("a.css"),
+        source,
+        &class_modules,
+        hash_options()
+      )
+      .scope_id(),
+      None
+    );
+
+    let mut stylesheet = parse_module(source);
+    let mut exports = compile(&mut stylesheet, config("a.css"))
+      .renamed
+      .into_iter()
+      .collect();
+    scope_locals(&mut stylesheet, &mut exports, &scope_id);
+    let exports = exports
+      .into_iter()
+      .map(|(local, names)| {
+        let names = names
+          .into_iter()
+          .map(|name| match name {
+            CssClassName::Local { name } => name.value.to_string(),
+            _ => unreachable!(),
+          })
+          .collect::<Vec<_>>();
+        (local.to_string(), names)
+      })
+      .collect::<std::collections::BTreeMap<_, _>>();
+    // The locals keep their names.
+    for (local, names) in [
+      ("a", vec!["a"]),
+      ("b", vec!["b"]),
+      ("c", vec!["c", "a"]),
+      ("d", vec!["d"]),
+      ("e", vec!["e"]),
+    ] {
+      assert_eq!(exports[local], names, "{local}");
+    }
+    let (css, _) = SWC_COMPILER
+      .codegen(
+        Default::default(),
+        &stylesheet,
+        SwcCssSourceMapGenConfig {
+          enable: false,
+          emit_columns: false,
+          inline_sources_content: false,
+        },
+      )
+      .expect("should codegen");
+    assert!(!css.contains(SCOPED_LOCAL_MARKER), "{css:?}");
+    for selector in [
+      format!(".a[{scope_id}] .b[{scope_id}]::before {{"),
+      format!(".c[{scope_id}] {{"),
+      format!(".e[{scope_id}] {{"),
+      ".a {".to_string(),
+      "@keyframes d {".to_string(),
+      "animation: d 1s".to_string(),
+    ] {
+      assert!(css.contains(&selector), "{selector} in {css}");
+    }
+  }
+
+  #[test]
+  fn test_write_exports_pathinfo() {
+    let mut exports = exports(&[("foo", &["_foo"])]);
+    let CssClassName::Local { name } = &mut exports[0][0] else {
+      unreachable!()
+    };
+    name.span = Span::new(BytePos(1), BytePos(4), Default::default());
+    // Imported above the exports of an ES module.
+    exports[0].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    for es_module in [false, true] {
+      let write = |pathinfo| {
+        let mut code = String::new();
+        let mut mappings = vec![];
+        write_exports(
+          &mut code,
+          &exports,
+          &modules_config(es_module, false, "asIs"),
+          pathinfo,
+          &mut RuntimeGlobals::default(),
+          Some(&mut mappings),
+          |from| Ok(from.to_string()),
+        )
+        .expect("should write");
+        (code, mappings)
+      };
+      let (code, mappings) = write(Some("./src/button.module.css"));
+      assert!(
+        code.starts_with("// CSS module: ./src/button.module.css\n"),
+        "{code}"
+      );
+      // The mapping still points at the line of the property.
+      let line = code.lines().position(|line| line.contains("\"foo\""));
+      assert_eq!(line, Some(mappings[0].1.line as usize), "{code}");
+      let (code, _) = write(None);
+      assert!(!code.contains("//"), "{code}");
+    }
+  }
+
+  #[test]
+  fn test_write_exports_custom_locals_convention() {
+    use heck::ToSnakeCase;
+
+    let exports = exports(&[
+      ("fooBar", &["_fooBar"]),
+      ("foo-bar", &["_foo-bar"]),
+      ("baz", &[]),
+    ]);
+    let modules = ModulesConfig {
+      locals_convention: LocalsConvention::custom(Arc::new(|name| {
+        // Drop the locals without a snake_case key.
+        let key = name.to_snake_case();
+        if key == name {
+          vec![]
+        } else {
+          vec![key]
+        }
+      })),
+      ..modules_config(false, false, "asIs")
+    };
+    let mut code = String::new();
+    write_exports(
+      &mut code,
+      &exports,
+      &modules,
+      None,
+      &mut RuntimeGlobals::default(),
+      None,
+      |from| Ok(from.to_string()),
+    )
+    .expect("should write");
+    assert_eq!(
+      code,
+      "module.exports = {\n  \"foo_bar\": \"_fooBar _foo-bar\",\n};\n"
+    );
+  }
+
+  #[test]
+  fn test_export_entries_source_selector() {
+    let source = r#"
+.button:hover > .icon { color: red; }
+#main, .title:not(.hidden) { color: blue; }
+.primary { composes: button; composes: a from "./a.css"; }
+"#;
+    let cm: Arc<SourceMap> = Default::default();
+    let mut ss = SWC_COMPILER
+      .parse_file(
+        cm.clone(),
+        "index.css",
+        source.to_string(),
+        ParserConfig {
+          css_modules: true,
+          ..Default::default()
+        },
+      )
+      .expect("should parse")
+      .inner;
+    let selectors = crate::visitors::collect_selectors(&ss, &cm);
+    let modules = modules_config(false, false, "asIs");
+    let config = ModulesTransformConfig::with_hash_options(
+      Path::new("index.css"),
+      source,
+      &modules,
+      hash_options(),
+    );
+    let mut exports: IndexMap<JsWord, _> = compile(&mut ss, config).renamed.into_iter().collect();
+    // Like the values of `:export`, which aren't part of any selector.
+    exports.insert(
+      "primaryColor".into(),
+      vec![CssClassName::Global {
+        name: Ident {
+          span: DUMMY_SP,
+          value: "red".into(),
+          raw: None,
+        },
+      }],
+    );
+    let entries = export_entries(
+      &exports,
+      &modules,
+      Some(&selectors),
+      &mut IndexSet::default(),
+      |from| Ok(from.to_string()),
+    )
+    .expect("should collect entries");
+    let source_selector = |key: &str| {
+      entries
+        .iter()
+        .find(|entry| entry.key == key)
+        .unwrap_or_else(|| panic!("missing {key}"))
+        .source_selector
+        .as_deref()
+    };
+    assert_eq!(source_selector("button"), Some(".button:hover > .icon"));
+    assert_eq!(source_selector("icon"), Some(".button:hover > .icon"));
+    assert_eq!(source_selector("main"), Some("#main"));
+    assert_eq!(source_selector("title"), Some(".title:not(.hidden)"));
+    assert_eq!(source_selector("hidden"), Some(".title:not(.hidden)"));
+    assert_eq!(source_selector("primary"), Some(".primary"));
+    assert_eq!(source_selector("primaryColor"), None);
+    let primary = entries
+      .iter()
+      .find(|entry| entry.key == "primary")
+      .expect("should export primary");
+    assert_eq!(
+      primary.values,
+      vec![
+        ExportValue::Static("primary".into()),
+        ExportValue::Static("button".into()),
+        ExportValue::Composed(r#"__webpack_require__("./a.css")["a"]"#.into()),
+      ]
+    );
+
+    let code = {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        &modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    // Rendered from the same entries, in the same order.
+    for (line, entry) in code.lines().skip(1).zip(&entries) {
+      assert!(
+        line.starts_with(&format!("  \"{}\": ", entry.key)),
+        "{line}"
+      );
+    }
+    assert_eq!(code.lines().count(), entries.len() + 2);
+  }
+
+  #[test]
+  fn test_export_entries_named_exports() {
+    let exports = exports(&[
+      ("foo-bar", &["a"]),
+      ("class", &["b"]),
+      ("zoo", &["c", "d"]),
+      ("1st", &["e"]),
+      ("$ok_1", &["f"]),
+    ]);
+    let named_exports = |modules: &ModulesConfig| {
+      let entries = export_entries(&exports, modules, None, &mut IndexSet::default(), |from| {
+        Ok(from.to_string())
+      })
+      .expect("should collect entries");
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      // The getters of the named exports, and of the default export.
+      let mut emitted = code
+        .lines()
+        .filter(|line| line.contains(": function () {"))
+        .filter_map(|line| line.trim_start().split('"').nth(1))
+        .filter(|key| *key != "default")
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+      emitted.sort();
+      let mut listed = entries
+        .iter()
+        .filter(|entry| entry.named_export)
+        .map(|entry| entry.key.clone())
+        .collect::<Vec<_>>();
+      listed.sort();
+      assert_eq!(listed, emitted, "{code}");
+      listed
+    };
+    assert!(named_exports(&modules_config(false, false, "camelCase")).is_empty());
+    assert_eq!(
+      named_exports(&modules_config(true, false, "camelCase")),
+      vec!["$ok_1", "fooBar", "ok1", "zoo"]
+    );
+    assert_eq!(
+      named_exports(&modules_config(true, true, "camelCaseOnly")),
+      vec!["fooBar", "ok1", "zoo"]
+    );
+    let entries = export_entries(
+      &exports,
+      &ModulesConfig {
+        export_class_names: ExportClassNames::Array,
+        ..modules_config(true, true, "camelCaseOnly")
+      },
+      None,
+      &mut IndexSet::default(),
+      |from| Ok(from.to_string()),
+    )
+    .expect("should collect entries");
+    let zoo = entries
+      .iter()
+      .find(|entry| entry.key == "zoo")
+      .expect("should export zoo");
+    assert!(zoo.is_array());
+  }
+
+  #[test]
+  fn test_write_exports_by_convention() {
+    let mut exports = exports(&[("foo-bar", &["_foo-bar"]), ("zoo", &["_zoo"])]);
+    exports[1].push(CssClassName::Import {
+      name: Ident {
+        span: DUMMY_SP,
+        value: "a-b".into(),
+        raw: None,
+      },
+      from: "./a.css".into(),
+    });
+    let write = |modules: &ModulesConfig| {
+      let mut code = String::new();
+      write_exports(
+        &mut code,
+        &exports,
+        modules,
+        None,
+        &mut RuntimeGlobals::default(),
+        None,
+        |from| Ok(from.to_string()),
+      )
+      .expect("should write");
+      code
+    };
+    let by_convention = |es_module, convention| ModulesConfig {
+      exports_by_convention: true,
+      ..modules_config(es_module, false, convention)
+    };
+    let composed =
+      r#"["_zoo", __webpack_require__("./a.css")["asIs"]["a-b"]].filter(Boolean).join(" ")"#;
+    assert_eq!(
+      write(&by_convention(false, "camelCase")),
+      format!(
+        r#"module.exports = {{
+  "asIs": {{
+    "foo-bar": "_foo-bar",
+    "zoo": {composed},
+  }},
+  "camelCase": {{
+    "fooBar": "_foo-bar",
+    "zoo": {composed},
+  }},
+}};
+"#
+      )
+    );
+    // A single convention is still nested, the object it's in doesn't depend on the others.
+    assert!(write(&by_convention(false, "camelCaseOnly")).starts_with(
+      r#"module.exports = {
+  "camelCase": {
+    "fooBar": "_foo-bar","#
+    ));
+    // The objects are the named exports of an ES module.
+    let code = write(&by_convention(true, "dashes"));
+    assert!(code.contains(r#"__WEBPACK_IMPORTED_MODULE_0__["default"]["asIs"]["a-b"]"#));
+    assert!(
+      code.contains(r#"  "dashes": function () { return __WEBPACK_DEFAULT_EXPORT__["dashes"]; },"#)
+    );
+    let entries = export_entries(
+      &exports,
+      &by_convention(true, "dashes"),
+      None,
+      &mut IndexSet::default(),
+      |from| Ok(from.to_string()),
+    )
+    .expect("should collect entries");
+    let keys = entries
+      .iter()
+      .map(|entry| (entry.convention, entry.key.as_str(), entry.named_export))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      keys,
+      vec![
+        (Some("asIs"), "foo-bar", false),
+        (Some("asIs"), "zoo", false),
+        (Some("dashes"), "foo-bar", false),
+        (Some("dashes"), "zoo", false),
+      ]
+    );
+    // Merged by default.
+    assert!(
+      write(&modules_config(false, false, "camelCase")).starts_with(
+        r#"module.exports = {
+  "foo-bar": "_foo-bar",
+  "fooBar": "_foo-bar","#
+      )
+    );
+  }
 }
@@ -1,21 +1,54 @@
+use std::path::Path;
+
+use indexmap::IndexMap;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use rspack_core::ModuleDependency;
+use rspack_core::{ModuleDependency, SpanExt};
 use rspack_error::{Diagnostic, DiagnosticKind};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet};
 use swc_core::{
-  common::pass::AstNodePath,
+  common::{pass::AstNodePath, BytePos, SourceMap, SourceMapper, Span, Spanned, DUMMY_SP},
   css::{
-    ast::{ImportHref, ImportPrelude, Stylesheet, Url, UrlValue},
-    visit::{AstParentKind, AstParentNodeRef, VisitAstPath, VisitWithPath},
+    ast::{
+      AtRule, AtRuleName, AtRulePrelude, AttributeSelector, ComplexSelector,
+      ComplexSelectorChildren, ComponentValue, CompoundSelector, CustomIdent, Declaration,
+      DeclarationName, Ident, ImportHref, ImportPrelude, MediaQueryList,
+      PseudoClassSelectorChildren, QualifiedRule, QualifiedRulePrelude, Rule, Str, Stylesheet,
+      SubclassSelector, Url, UrlValue, WqName,
+    },
+    codegen::{
+      writer::basic::{BasicCssWriter, BasicCssWriterConfig},
+      CodeGenerator, CodegenConfig, Emit,
+    },
+    modules::CssClassName,
+    visit::{
+      AstParentKind, AstParentNodeRef, Visit, VisitAstPath, VisitMut, VisitMutWith, VisitWith,
+      VisitWithPath,
+    },
   },
+  ecma::atoms::JsWord,
 };
 
 use crate::{
   dependency::{CssImportDependency, CssUrlDependency},
-  utils::normalize_url,
+  plugin::InvalidEscape,
+  utils::{
+    css_unescape_with, is_normalized_url, module_request, normalize_url_with_options,
+    url_to_dependency_request, NormalizeUrlOptions, TransformCounters, SCOPED_LOCAL_MARKER,
+  },
 };
 
-static IS_MODULE_REQUEST: Lazy<Regex> = Lazy::new(|| Regex::new(r"^~").expect("TODO:"));
+static VALUE_IMPORTS: Lazy<Regex> = Lazy::new(|| {
+  Regex::new(r#"^(.+?|\([\s\S]+?\))\s+from\s+("[^"]*"|'[^']*'|[\w-]+)$"#).expect("Invalid RegExp")
+});
+
+static VALUE_IMPORT: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"^([\w-]+)(?:\s+as\s+([\w-]+))?$").expect("Invalid RegExp"));
+
+static VALUE_DEFINITION: Lazy<Regex> =
+  Lazy::new(|| Regex::new(r"^([\w-]+)(?:\s*:\s*|\s+)([\s\S]*)$").expect("Invalid RegExp"));
+
+static VALUE_WORD: Lazy<Regex> = Lazy::new(|| Regex::new(r"[\w-]+").expect("Invalid RegExp"));
 
 pub fn as_parent_path(ast_path: &AstNodePath<AstParentNodeRef<'_>>) -> Vec<AstParentKind> {
   ast_path.iter().map(|n| n.kind()).collect()
@@ -23,13 +56,21 @@ pub fn as_parent_path(ast_path: &AstNodePath<AstParentNodeRef<'_>>) -> Vec<AstPa
 
 pub fn analyze_dependencies(
   ss: &mut Stylesheet,
+  context: &Path,
   code_generation_dependencies: &mut Vec<Box<dyn ModuleDependency>>,
   diagnostics: &mut Vec<Diagnostic>,
+  relative_urls: bool,
+  url_options: NormalizeUrlOptions,
+  counters: Option<&TransformCounters>,
 ) -> Vec<Box<dyn ModuleDependency>> {
   let mut v = Analyzer {
     deps: Vec::new(),
+    context,
     code_generation_dependencies,
     diagnostics,
+    relative_urls,
+    url_options,
+    counters,
     // in_support_contdition: false,
   };
   ss.visit_with_path(&mut v, &mut Default::default());
@@ -40,13 +81,18 @@ pub fn analyze_dependencies(
 #[derive(Debug)]
 struct Analyzer<'a> {
   deps: Vec<Box<dyn ModuleDependency>>,
+  /// The directory of the stylesheet, relative `url()`s are requested from it.
+  context: &'a Path,
   code_generation_dependencies: &'a mut Vec<Box<dyn ModuleDependency>>,
   diagnostics: &'a mut Vec<Diagnostic>,
+  relative_urls: bool,
+  url_options: NormalizeUrlOptions,
+  counters: Option<&'a TransformCounters>,
   // in_support_contdition: bool,
 }
 
-fn replace_module_request_prefix(specifier: String, diagnostics: &mut Vec<Diagnostic>) -> String {
-  if IS_MODULE_REQUEST.is_match(&specifier) {
+fn warn_deprecated_module_request(specifier: &str, diagnostics: &mut Vec<Diagnostic>) {
+  if module_request(specifier).is_some() {
     diagnostics.push(
       Diagnostic::warn(
         "Deprecated '~'".to_string(),
@@ -56,12 +102,25 @@ fn replace_module_request_prefix(specifier: String, diagnostics: &mut Vec<Diagno
       )
       .with_kind(DiagnosticKind::Css),
     );
-    IS_MODULE_REQUEST.replace(&specifier, "").to_string()
-  } else {
-    specifier
   }
 }
 
+fn replace_module_request_prefix(specifier: String, diagnostics: &mut Vec<Diagnostic>) -> String {
+  warn_deprecated_module_request(&specifier, diagnostics);
+  match module_request(&specifier) {
+    Some(request) => request.to_string(),
+    None => specifier,
+  }
+}
+
+fn media_query_list_to_string(media: &MediaQueryList) -> Result<String, std::fmt::Error> {
+  let mut s = String::new();
+  let wr = BasicCssWriter::new(&mut s, None, BasicCssWriterConfig::default());
+  let mut gen = CodeGenerator::new(wr, CodegenConfig { minify: false });
+  gen.emit(media)?;
+  Ok(s)
+}
+
 impl VisitAstPath for Analyzer<'_> {
   fn visit_import_prelude<'ast: 'r, 'r>(
     &mut self,
@@ -77,10 +136,30 @@ impl VisitAstPath for Analyzer<'_> {
     };
     if let Some(specifier) = specifier {
       let specifier = replace_module_request_prefix(specifier, self.diagnostics);
+      let media = n
+        .import_conditions
+        .as_ref()
+        .and_then(|conditions| conditions.media.as_ref())
+        .and_then(|box media| match media_query_list_to_string(media) {
+          Ok(media) => Some(media),
+          Err(_) => {
+            self.diagnostics.push(
+              Diagnostic::error(
+                "CSS".to_string(),
+                format!("Failed to generate the media queries of `@import \"{specifier}\"`."),
+                n.span.real_lo() as usize,
+                n.span.real_hi() as usize,
+              )
+              .with_kind(DiagnosticKind::Css),
+            );
+            None
+          }
+        });
       self.deps.push(Box::new(CssImportDependency::new(
         specifier,
         Some(n.span.into()),
         as_parent_path(ast_path),
+        media,
       )));
     }
   }
@@ -104,21 +183,694 @@ impl VisitAstPath for Analyzer<'_> {
     u.visit_children_with_path(self, ast_path);
     // Wait for @supports
     // if !self.in_support_contdition {
-    let specifier = u.value.as_ref().map(|box v| match v {
-      UrlValue::Str(s) => s.value.to_string(),
-      UrlValue::Raw(r) => r.value.to_string(),
-    });
-    if let Some(specifier) = specifier && !specifier.is_empty(){
-    let mut specifier = replace_module_request_prefix(specifier, self.diagnostics);
-    specifier = normalize_url(&specifier);
+    // `url()` without a value is as empty as `url("")`, both are warned about and skipped.
+    // A quoted url is normalized as written, so the white space within its quotes is kept.
+    let specifier = u
+      .value
+      .as_ref()
+      .map(|box v| match v {
+        UrlValue::Str(s) => s
+          .raw
+          .as_ref()
+          .map_or_else(|| s.value.to_string(), ToString::to_string),
+        UrlValue::Raw(r) => r.value.to_string(),
+      })
+      .unwrap_or_default();
+    if let Some(counters) = self.counters {
+      counters.count_url(is_normalized_url(&specifier, self.url_options));
+    }
+    let (normalized, diagnostics) = normalize_url_with_options(&specifier, self.url_options);
+    self
+      .diagnostics
+      .extend(diagnostics.into_iter().map(|mut diagnostic| {
+        diagnostic.start = u.span.real_lo() as usize;
+        diagnostic.end = u.span.real_hi() as usize;
+        diagnostic
+      }));
+    if normalized.is_empty() {
+      return;
+    }
+    // Data uris, urls with a scheme, root relative urls and fragments are kept as written.
+    let Some(request) = url_to_dependency_request(&normalized, self.context) else {
+      return;
+    };
+    warn_deprecated_module_request(&normalized, self.diagnostics);
     let dep = Box::new(CssUrlDependency::new(
-      specifier,
+      request,
       Some(u.span.into()),
       as_parent_path(ast_path),
+      self.relative_urls,
     ));
     self.deps.push(dep.clone());
     self.code_generation_dependencies.push(dep);
-  // }
+    // }
+  }
+}
+
+/// Collect the class names selected in global scope, i.e. `:global(.foo)` and `:global .foo`.
+///
+/// Must run before the css modules transform, which strips the `:global` pseudo classes.
+pub fn collect_global_classes(ss: &Stylesheet) -> Vec<JsWord> {
+  let mut v = ClassCollector::default();
+  ss.visit_with(&mut v);
+  v.globals
+}
+
+/// Collect the classes referenced by `composes` without a `from` that no selector of the
+/// stylesheet defines, the css modules transform drops them.
+///
+/// Must run before the css modules transform, which renames the classes.
+pub fn collect_undefined_composes(ss: &Stylesheet) -> Vec<Ident> {
+  let mut v = ClassCollector::default();
+  ss.visit_with(&mut v);
+  v.composes
+    .into_iter()
+    .filter(|class| !v.locals.contains(&class.value))
+    .collect()
+}
+
+/// The spans of the selectors of `ss` that select no local class or id, e.g. `div`, `:global(.a)`
+/// or `:global .a .b`, like css-loader's `pure` mode does. `.a :global(.b)` is scoped by `.a`.
+///
+/// Must run before the css modules transform, which strips the `:global` pseudo classes.
+pub fn collect_impure_selectors(ss: &Stylesheet) -> Vec<Span> {
+  let mut v = ImpureSelectorCollector::default();
+  ss.visit_with(&mut v);
+  v.selectors
+}
+
+#[derive(Debug, Default)]
+struct ImpureSelectorCollector {
+  selectors: Vec<Span>,
+}
+
+impl Visit for ImpureSelectorCollector {
+  fn visit_qualified_rule(&mut self, n: &QualifiedRule) {
+    if let QualifiedRulePrelude::SelectorList(list) = &n.prelude {
+      self.selectors.extend(
+        list
+          .children
+          .iter()
+          .filter(|selector| !selects_local(selector, false))
+          .map(|selector| selector.span),
+      );
+    }
+    n.block.visit_with(self);
+  }
+}
+
+/// Whether `n` has a class or id selector in local scope, `is_global_mode` at its start.
+fn selects_local(n: &ComplexSelector, mut is_global_mode: bool) -> bool {
+  for child in &n.children {
+    let ComplexSelectorChildren::CompoundSelector(selector) = child else {
+      continue;
+    };
+    for sel in &selector.subclass_selectors {
+      match sel {
+        SubclassSelector::Class(_) | SubclassSelector::Id(_) if !is_global_mode => return true,
+        SubclassSelector::PseudoClass(pseudo) => match (&*pseudo.name.value, &pseudo.children) {
+          (name @ ("global" | "local"), Some(children)) => {
+            if let Some(PseudoClassSelectorChildren::ComplexSelector(complex_selector)) =
+              children.get(0)
+              && selects_local(complex_selector, name == "global")
+            {
+              return true;
+            }
+          }
+          ("global", None) => is_global_mode = true,
+          ("local", None) => is_global_mode = false,
+          _ => {}
+        },
+        _ => {}
+      }
+    }
+  }
+  false
+}
+
+#[derive(Debug, Default)]
+struct ClassCollector {
+  globals: Vec<JsWord>,
+  locals: FxHashSet<JsWord>,
+  composes: Vec<Ident>,
+  is_global_mode: bool,
+}
+
+impl ClassCollector {
+  fn add(&mut self, class: &JsWord) {
+    if !self.globals.contains(class) {
+      self.globals.push(class.clone());
+    }
+  }
+}
+
+impl Visit for ClassCollector {
+  fn visit_complex_selector(&mut self, n: &ComplexSelector) {
+    let old_is_global_mode = self.is_global_mode;
+    for child in &n.children {
+      let ComplexSelectorChildren::CompoundSelector(selector) = child else {
+        continue;
+      };
+      for sel in &selector.subclass_selectors {
+        match sel {
+          SubclassSelector::Class(class) if self.is_global_mode => self.add(&class.text.value),
+          SubclassSelector::Class(class) => {
+            self.locals.insert(class.text.value.clone());
+          }
+          SubclassSelector::PseudoClass(pseudo) => match (&*pseudo.name.value, &pseudo.children) {
+            (name @ ("global" | "local"), Some(children)) => {
+              if let Some(PseudoClassSelectorChildren::ComplexSelector(complex_selector)) =
+                children.get(0)
+              {
+                let old_is_global_mode = self.is_global_mode;
+                self.is_global_mode = name == "global";
+                complex_selector.visit_with(self);
+                self.is_global_mode = old_is_global_mode;
+              }
+            }
+            ("global", None) => self.is_global_mode = true,
+            ("local", None) => self.is_global_mode = false,
+            _ => {}
+          },
+          _ => {}
+        }
+      }
+    }
+    self.is_global_mode = old_is_global_mode;
+  }
+
+  fn visit_declaration(&mut self, n: &Declaration) {
+    let DeclarationName::Ident(name) = &n.name else {
+      return;
+    };
+    if &*name.value != "composes" {
+      return;
+    }
+    // `composes: a b from "./a.css"` and `composes: a from global` don't compose local classes.
+    if let [.., ComponentValue::Ident(from), _] = &n.value[..] && &*from.value == "from" {
+      return;
+    }
+    for value in &n.value {
+      if let ComponentValue::Ident(box class) = value {
+        self.composes.push(class.clone());
+      }
+    }
+  }
+}
+
+/// The selector written for the rule each class and id selector of `ss` is part of, by the start of
+/// their span, e.g. both `.button` and `.icon` of `.button:hover > .icon` map to that selector.
+///
+/// The css modules transform keeps those spans, so the locals it renames can be mapped back to the
+/// selectors declaring them, see [css_modules_export_entries](crate::utils::css_modules_export_entries).
+pub fn collect_selectors(ss: &Stylesheet, cm: &SourceMap) -> HashMap<BytePos, String> {
+  let mut v = SelectorCollector {
+    cm,
+    selectors: HashMap::default(),
+    selector: None,
+  };
+  ss.visit_with(&mut v);
+  v.selectors
+}
+
+struct SelectorCollector<'a> {
+  cm: &'a SourceMap,
+  selectors: HashMap<BytePos, String>,
+  /// Of the outermost complex selector being visited, `:not(.a)` is part of the selector around.
+  selector: Option<String>,
+}
+
+impl Visit for SelectorCollector<'_> {
+  fn visit_complex_selector(&mut self, n: &ComplexSelector) {
+    if self.selector.is_some() {
+      return n.visit_children_with(self);
+    }
+    self.selector = self.cm.span_to_snippet(n.span).ok();
+    n.visit_children_with(self);
+    self.selector = None;
+  }
+
+  fn visit_subclass_selector(&mut self, n: &SubclassSelector) {
+    let span = match n {
+      SubclassSelector::Class(class) => class.span,
+      SubclassSelector::Id(id) => id.span,
+      _ => return n.visit_children_with(self),
+    };
+    if let Some(selector) = &self.selector {
+      self.selectors.insert(span.lo, selector.clone());
+    }
+  }
+}
+
+/// A `@value` declared by a css module.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CssModuleValue {
+  /// `@value primary: #fff;`, references to values declared before are replaced.
+  Local(String),
+  /// `@value primary as brand from "./colors.css";`
+  Import { name: String, from: String },
+}
+
+/// Scope the selectors of the locals the css modules transform gave names with
+/// [LocalScope::Attribute](crate::plugin::LocalScope::Attribute) by the `scope_id` attribute, e.g.
+/// `.a .b::before` becomes `.a[data-v-1d2e3f4a] .b[data-v-1d2e3f4a]::before`, and give those
+/// locals their names back in `ss` and `exports`.
+///
+/// Must run right after the css modules transform.
+pub fn scope_locals(
+  ss: &mut Stylesheet,
+  exports: &mut IndexMap<JsWord, Vec<CssClassName>>,
+  scope_id: &str,
+) {
+  ss.visit_mut_with(&mut LocalScoper { scope_id });
+  for name in exports.values_mut().flatten() {
+    if let CssClassName::Local { name } = name
+      && let Some(local) = unmark_scoped_local(&name.value)
+    {
+      name.value = local;
+    }
+  }
+}
+
+fn unmark_scoped_local(name: &str) -> Option<JsWord> {
+  name.strip_suffix(SCOPED_LOCAL_MARKER).map(JsWord::from)
+}
+
+struct LocalScoper<'a> {
+  scope_id: &'a str,
+}
+
+impl VisitMut for LocalScoper<'_> {
+  fn visit_mut_compound_selector(&mut self, n: &mut CompoundSelector) {
+    let scoped = n.subclass_selectors.iter().any(|sel| match sel {
+      SubclassSelector::Class(sel) => sel.text.value.ends_with(SCOPED_LOCAL_MARKER),
+      SubclassSelector::Id(sel) => sel.text.value.ends_with(SCOPED_LOCAL_MARKER),
+      _ => false,
+    });
+    n.visit_mut_children_with(self);
+    if !scoped {
+      return;
+    }
+    // Nothing may follow a pseudo element, `.a::before[data-v-1d2e3f4a]` is invalid.
+    let index = n
+      .subclass_selectors
+      .iter()
+      .position(|sel| matches!(sel, SubclassSelector::PseudoElement(_)))
+      .unwrap_or(n.subclass_selectors.len());
+    n.subclass_selectors.insert(
+      index,
+      SubclassSelector::Attribute(Box::new(AttributeSelector {
+        span: DUMMY_SP,
+        name: WqName {
+          span: DUMMY_SP,
+          prefix: None,
+          value: Ident {
+            span: DUMMY_SP,
+            value: self.scope_id.into(),
+            raw: None,
+          },
+        },
+        matcher: None,
+        value: None,
+        modifier: None,
+      })),
+    );
+  }
+
+  fn visit_mut_ident(&mut self, n: &mut Ident) {
+    if let Some(local) = unmark_scoped_local(&n.value) {
+      n.value = local;
+    }
+  }
+
+  // Keyframes names.
+  fn visit_mut_custom_ident(&mut self, n: &mut CustomIdent) {
+    if let Some(local) = unmark_scoped_local(&n.value) {
+      n.value = local;
+    }
+  }
+
+  fn visit_mut_str(&mut self, n: &mut Str) {
+    if let Some(local) = unmark_scoped_local(&n.value) {
+      n.value = local;
+    }
+  }
+}
+
+/// Remove the top level `@value` rules of a css module and return the values they declare, in
+/// order of declaration.
+///
+/// Values are only exported, usages in the stylesheet itself are left as is.
+pub fn extract_values(ss: &mut Stylesheet, cm: &SourceMap) -> Vec<(String, CssModuleValue)> {
+  let mut preludes = vec![];
+  ss.rules.retain(|rule| match rule {
+    Rule::AtRule(box AtRule {
+      name: AtRuleName::Ident(name),
+      prelude,
+      ..
+    }) if &*name.value == "value" => {
+      if let Some(box AtRulePrelude::ListOfComponentValues(prelude)) = prelude
+        && let Ok(prelude) = cm.span_to_snippet(prelude.span)
+      {
+        preludes.push(prelude);
+      }
+      false
+    }
+    _ => true,
+  });
+  parse_values(preludes.into_iter())
+}
+
+/// Remove the top level `:export { name: value; }` rules of a css module and return the pairs they
+/// declare, in order of declaration.
+pub fn extract_exports(
+  ss: &mut Stylesheet,
+  cm: &SourceMap,
+  invalid_escape: InvalidEscape,
+) -> Vec<(String, String)> {
+  let mut exports = vec![];
+  ss.rules.retain(|rule| match rule {
+    Rule::QualifiedRule(box QualifiedRule { prelude, block, .. })
+      if cm
+        .span_to_snippet(prelude.span())
+        .is_ok_and(|prelude| prelude.trim() == ":export") =>
+    {
+      for value in &block.value {
+        // Names are unescaped like class names, `a\:b: 1` exports `a:b`. Values are kept as written.
+        if let ComponentValue::Declaration(declaration) = value
+          && let Ok(name) = cm.span_to_snippet(declaration.name.span())
+          && let Ok(declaration) = cm.span_to_snippet(declaration.span)
+          && let Some(value) = declaration
+            .strip_prefix(name.as_str())
+            .and_then(|value| value.trim_start().strip_prefix(':'))
+        {
+          exports.push((
+            css_unescape_with(&name, invalid_escape).into_owned(),
+            value.trim().to_string(),
+          ));
+        }
+      }
+      false
+    }
+    _ => true,
+  });
+  exports
+}
+
+fn parse_values(preludes: impl Iterator<Item = String>) -> Vec<(String, CssModuleValue)> {
+  let mut values: Vec<(String, CssModuleValue)> = Vec::new();
+  let local = |values: &[(String, CssModuleValue)], name: &str| {
+    values.iter().rev().find_map(|(key, value)| match value {
+      CssModuleValue::Local(value) if key == name => Some(value.clone()),
+      _ => None,
+    })
+  };
+  for prelude in preludes {
+    let prelude = prelude.trim();
+    if let Some(caps) = VALUE_IMPORTS.captures(prelude) {
+      let from = &caps[2];
+      let from = if from.starts_with(['"', '\'']) {
+        from[1..from.len() - 1].to_string()
+      } else if let Some(path) = local(&values, from) {
+        // `@value colors: "./colors.css"; @value primary from colors;`
+        path.trim_matches(|c| c == '"' || c == '\'').to_string()
+      } else {
+        continue;
+      };
+      let imports = caps[1].trim_start_matches('(').trim_end_matches(')');
+      for import in imports.split(',') {
+        if let Some(caps) = VALUE_IMPORT.captures(import.trim()) {
+          let name = caps[1].to_string();
+          let key = caps
+            .get(2)
+            .map_or(name.clone(), |alias| alias.as_str().to_string());
+          values.push((
+            key,
+            CssModuleValue::Import {
+              name,
+              from: from.clone(),
+            },
+          ));
+        }
+      }
+    } else if let Some(caps) = VALUE_DEFINITION.captures(prelude) {
+      let value = VALUE_WORD.replace_all(caps[2].trim(), |word: &regex::Captures| {
+        local(&values, &word[0]).unwrap_or_else(|| word[0].to_string())
+      });
+      values.push((
+        caps[1].to_string(),
+        CssModuleValue::Local(value.into_owned()),
+      ));
+    }
+  }
+  values
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use swc_core::css::parser::parser::ParserConfig;
+
+  use super::*;
+  use crate::swc_css_compiler::SWC_COMPILER;
+
+  fn parse_module(source: &str) -> Stylesheet {
+    SWC_COMPILER
+      .parse_file(
+        Default::default(),
+        "index.css",
+        source.to_string(),
+        ParserConfig {
+          css_modules: true,
+          ..Default::default()
+        },
+      )
+      .expect("should parse")
+      .inner
+  }
+
+  fn global_classes(source: &str) -> Vec<String> {
+    collect_global_classes(&parse_module(source))
+      .into_iter()
+      .map(|class| class.to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_collect_global_classes() {
+    assert_eq!(
+      global_classes(
+        ".foo {} .one :local(.bar) {} .two :global(.baz) {} .three :local .bav {} .four :global .bax {}"
+      ),
+      vec!["baz", "bax"]
+    );
+    assert_eq!(
+      global_classes(":global(.a .b), .c {} :global .d :local .e {} :global(.a) {}"),
+      vec!["a", "b", "d"]
+    );
+  }
+
+  #[test]
+  fn test_collect_impure_selectors() {
+    let impure_selectors = |source: &str| {
+      let cm: Arc<SourceMap> = Default::default();
+      let ss = SWC_COMPILER
+        .parse_file(
+          cm.clone(),
+          "index.css",
+          source.to_string(),
+          ParserConfig {
+            css_modules: true,
+            ..Default::default()
+          },
+        )
+        .expect("should parse")
+        .inner;
+      collect_impure_selectors(&ss)
+        .into_iter()
+        .map(|span| cm.span_to_snippet(span).expect("should have the snippet"))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      impure_selectors(
+        ".a {} #b {} .c :global(.d) {} :global(.e) .f {} :local(.g) {} :global .h :local .i {}"
+      ),
+      Vec::<String>::new()
+    );
+    assert_eq!(
+      impure_selectors(
+        "div {} :global(.a) {} .b, :global(.c) {} :global .d .e {} @media print { :global(.f) {} }"
+      ),
+      vec![
+        "div",
+        ":global(.a)",
+        ":global(.c)",
+        ":global .d .e",
+        ":global(.f)"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_collect_undefined_composes() {
+    let undefined_composes = |source| {
+      collect_undefined_composes(&parse_module(source))
+        .into_iter()
+        .map(|class| class.value.to_string())
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      undefined_composes(
+        ".a { composes: b missing; } .b {} .c :local(.d) { composes: d; } :global(.e) {}
+        .f { composes: e; } .g { composes: x y from \"./x.css\"; composes: z from global; }"
+      ),
+      vec!["missing", "e"]
+    );
+    assert!(undefined_composes(".a { color: red; } .b { composes: a; }").is_empty());
+  }
+
+  #[test]
+  fn test_analyze_image_set_urls() {
+    let requests = |source: &str| {
+      let mut code_generation_dependencies = vec![];
+      let mut diagnostics = vec![];
+      let deps = analyze_dependencies(
+        &mut parse_module(source),
+        Path::new("/app/src"),
+        &mut code_generation_dependencies,
+        &mut diagnostics,
+        false,
+        Default::default(),
+        None,
+      );
+      assert!(diagnostics.is_empty(), "{diagnostics:?}");
+      assert_eq!(code_generation_dependencies.len(), deps.len());
+      deps
+        .iter()
+        .map(|dep| dep.request().to_string())
+        .collect::<Vec<_>>()
+    };
+    for function in ["image-set", "-webkit-image-set"] {
+      assert_eq!(
+        requests(&format!(
+          ".a {{ background-image: {function}(url(a%20b.png) 1x, url('./c%20d.png') 2x, url(d.png) type(\"image/png\")); }}"
+        )),
+        vec!["/app/src/a b.png", "/app/src/c d.png", "/app/src/d.png"],
+        "{function}"
+      );
     }
+    assert_eq!(
+      requests(".a { background: url(a.png), image-set(url(b.png) 1x, url(c.png) 2x) no-repeat; }"),
+      vec!["/app/src/a.png", "/app/src/b.png", "/app/src/c.png"]
+    );
+  }
+
+  #[test]
+  fn test_analyze_urls_kept_as_written() {
+    let mut code_generation_dependencies = vec![];
+    let mut diagnostics = vec![];
+    let deps = analyze_dependencies(
+      &mut parse_module(
+        ".a { a: url(data:image/png;base64,AAAA); b: url(https://rspack.dev/a.png); c: url(#ccc); d: url(/a.png); e: url(~pkg/a.png); }",
+      ),
+      Path::new("/app/src"),
+      &mut code_generation_dependencies,
+      &mut diagnostics,
+      false,
+      Default::default(),
+      None,
+    );
+    let requests = deps.iter().map(|dep| dep.request()).collect::<Vec<_>>();
+    assert_eq!(requests, ["pkg/a.png"]);
+    assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+  }
+
+  #[test]
+  fn test_parse_values() {
+    let values = parse_values(
+      [
+        "primary: #fff",
+        "secondary blue",
+        "border: 1px solid primary",
+        "colors: \"./colors.css\"",
+        "brand, accent as highlight from \"./theme.css\"",
+        "(danger) from colors",
+      ]
+      .into_iter()
+      .map(String::from),
+    );
+    let local = |value: &str| CssModuleValue::Local(value.to_string());
+    let import = |name: &str, from: &str| CssModuleValue::Import {
+      name: name.to_string(),
+      from: from.to_string(),
+    };
+    assert_eq!(
+      values,
+      vec![
+        ("primary".to_string(), local("#fff")),
+        ("secondary".to_string(), local("blue")),
+        ("border".to_string(), local("1px solid #fff")),
+        ("colors".to_string(), local("\"./colors.css\"")),
+        ("brand".to_string(), import("brand", "./theme.css")),
+        ("highlight".to_string(), import("accent", "./theme.css")),
+        ("danger".to_string(), import("danger", "./colors.css")),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_extract_exports() {
+    let cm: Arc<SourceMap> = Default::default();
+    let mut stylesheet = SWC_COMPILER
+      .parse_file(
+        cm.clone(),
+        "index.css",
+        ":export { primaryColor: #fff; border: 1px solid var(--border); a\\:b\\31: 1 } .btn {} \
+         @media print { :export { nested: 1 } }"
+          .to_string(),
+        ParserConfig {
+          css_modules: true,
+          ..Default::default()
+        },
+      )
+      .expect("should parse")
+      .inner;
+    assert_eq!(
+      extract_exports(&mut stylesheet, &cm, InvalidEscape::Replace),
+      vec![
+        ("primaryColor".to_string(), "#fff".to_string()),
+        ("border".to_string(), "1px solid var(--border)".to_string()),
+        ("a:b1".to_string(), "1".to_string()),
+      ]
+    );
+    // Only top level blocks are exports, the rest of the stylesheet is left as is.
+    assert_eq!(stylesheet.rules.len(), 2);
+
+    let source = r":export { a\110000 b: 1 }";
+    let exports = |invalid_escape| {
+      let cm: Arc<SourceMap> = Default::default();
+      let mut stylesheet = SWC_COMPILER
+        .parse_file(
+          cm.clone(),
+          "index.css",
+          source.to_string(),
+          ParserConfig {
+            css_modules: true,
+            ..Default::default()
+          },
+        )
+        .expect("should parse")
+        .inner;
+      extract_exports(&mut stylesheet, &cm, invalid_escape)
+    };
+    assert_eq!(
+      exports(InvalidEscape::Replace),
+      vec![("a\u{FFFD}b".to_string(), "1".to_string())]
+    );
+    assert_eq!(
+      exports(InvalidEscape::Keep),
+      vec![(r"a\110000 b".to_string(), "1".to_string())]
+    );
   }
 }
@@ -0,0 +1,27 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _amodulecss = __webpack_require__.ir(__webpack_require__("./a.module.css"));
+console.log(_amodulecss.default);
+__webpack_require__.el("./lazy.module.css").then(__webpack_require__.bind(__webpack_require__, "./lazy.module.css")).then(__webpack_require__.ir).then((lazy)=>console.log(lazy));
+},
+"./a.module.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "a": ["a___6895ee2", __webpack_require__("./lazy.module.css")["lazy"]].filter(Boolean).join(" "),
+};
+},
+"./lazy.module.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "lazy": "lazy___3ccf870",
+};
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
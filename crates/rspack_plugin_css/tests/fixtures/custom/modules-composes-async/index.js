@@ -0,0 +1,4 @@
+import a from "./a.module.css";
+
+console.log(a);
+import("./lazy.module.css").then(lazy => console.log(lazy));
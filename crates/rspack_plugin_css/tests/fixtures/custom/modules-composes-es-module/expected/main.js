@@ -0,0 +1,39 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _indexcss = __webpack_require__.ir(__webpack_require__("./index.css"));
+console.log(_indexcss.default.simple);
+},
+"./a.css": function (module, exports, __webpack_require__) {
+__webpack_require__.r(exports);
+var __WEBPACK_DEFAULT_EXPORT__ = {
+  "bar": "a__bar",
+  "foo": "a__foo",
+};
+__webpack_require__.d(exports, {
+  "bar": function () { return __WEBPACK_DEFAULT_EXPORT__["bar"]; },
+  "foo": function () { return __WEBPACK_DEFAULT_EXPORT__["foo"]; },
+  "default": function () { return __WEBPACK_DEFAULT_EXPORT__; },
+});
+},
+"./index.css": function (module, exports, __webpack_require__) {
+__webpack_require__.r(exports);
+var __WEBPACK_IMPORTED_MODULE_0__ = __webpack_require__("./a.css");
+var __WEBPACK_DEFAULT_EXPORT__ = {
+  "simple": ["index__simple", __WEBPACK_IMPORTED_MODULE_0__["default"]["foo"], __WEBPACK_IMPORTED_MODULE_0__["default"]["bar"]].filter(Boolean).join(" "),
+};
+__webpack_require__.d(exports, {
+  "simple": function () { return __WEBPACK_DEFAULT_EXPORT__["simple"]; },
+  "default": function () { return __WEBPACK_DEFAULT_EXPORT__; },
+});
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
@@ -0,0 +1,3 @@
+import style from "./index.css";
+
+console.log(style.simple);
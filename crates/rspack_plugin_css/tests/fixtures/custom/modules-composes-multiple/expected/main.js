@@ -0,0 +1,32 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _indexcss = __webpack_require__.ir(__webpack_require__("./index.css"));
+console.log(_indexcss.default.simple);
+},
+"./a.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "foo": "a__foo",
+};
+},
+"./b.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "bar": "b__bar",
+  "foo": "b__foo",
+};
+},
+"./index.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "simple": ["index__simple", __webpack_require__("./a.css")["foo"], __webpack_require__("./b.css")["foo"], __webpack_require__("./b.css")["bar"]].filter(Boolean).join(" "),
+};
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
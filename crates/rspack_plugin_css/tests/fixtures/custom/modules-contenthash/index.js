@@ -0,0 +1,4 @@
+import a from './a.module.css'
+import b from './b.module.css'
+
+console.log(a, b)
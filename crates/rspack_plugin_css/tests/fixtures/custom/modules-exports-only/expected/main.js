@@ -0,0 +1,26 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _stylemodulecss = __webpack_require__.ir(__webpack_require__("./style.module.css"));
+console.log(_stylemodulecss.default);
+},
+"./base.module.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "base": "base__a8df506c",
+};
+},
+"./style.module.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "button": ["button___980c5b5", __webpack_require__("./base.module.css")["base"]].filter(Boolean).join(" "),
+};
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
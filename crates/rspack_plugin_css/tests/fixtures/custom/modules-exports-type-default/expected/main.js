@@ -0,0 +1,29 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _stylemodulecss = __webpack_require__.ir(__webpack_require__("./style.module.css"));
+console.log(_stylemodulecss.default.button, _stylemodulecss.default['icon-large']);
+console.log(_stylemodulecss.button, _stylemodulecss['icon-large']);
+console.log(_stylemodulecss.button);
+},
+"./style.module.css": function (module, exports, __webpack_require__) {
+__webpack_require__.r(exports);
+var __WEBPACK_DEFAULT_EXPORT__ = {
+  "button": "button___980c5b5",
+  "icon-large": "icon-large__c1f12492",
+};
+__webpack_require__.d(exports, {
+  "button": function () { return __WEBPACK_DEFAULT_EXPORT__["button"]; },
+  "default": function () { return __WEBPACK_DEFAULT_EXPORT__; },
+});
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
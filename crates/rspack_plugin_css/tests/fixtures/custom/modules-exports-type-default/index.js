@@ -0,0 +1,7 @@
+import styles from './style.module.css'
+import * as namespace from './style.module.css'
+import { button } from './style.module.css'
+
+console.log(styles.button, styles['icon-large'])
+console.log(namespace.button, namespace['icon-large'])
+console.log(button)
@@ -0,0 +1,24 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _stylemodulecss = __webpack_require__.ir(__webpack_require__("./style.module.css"));
+console.log(_stylemodulecss.button, _stylemodulecss['icon-large']);
+console.log(_stylemodulecss.button);
+},
+"./style.module.css": function (module, exports, __webpack_require__) {
+__webpack_require__.r(exports);
+__webpack_require__.d(exports, {
+  "button": function () { return "button___980c5b5"; },
+  "icon-large": function () { return "icon-large__c1f12492"; },
+});
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
@@ -0,0 +1,5 @@
+import * as styles from './style.module.css'
+import { button } from './style.module.css'
+
+console.log(styles.button, styles['icon-large'])
+console.log(button)
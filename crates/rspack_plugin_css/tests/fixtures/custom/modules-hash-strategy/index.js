@@ -0,0 +1,4 @@
+import a from "./a/style.module.css";
+import b from "./b/style.module.css";
+
+console.log(a, b);
@@ -0,0 +1,4 @@
+import button from './button.module.css'
+import card from './card.module.css'
+
+console.log(button, card)
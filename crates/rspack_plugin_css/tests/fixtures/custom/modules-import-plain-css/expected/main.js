@@ -0,0 +1,24 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _amodulecss = __webpack_require__.ir(__webpack_require__("./a.module.css"));
+console.log(_amodulecss.default);
+},
+"./a.module.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "a": "a___6895ee2 b___7e19687",
+  "b": "b___7e19687",
+};
+},
+"./normal.css": function (module, exports, __webpack_require__) {
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
@@ -0,0 +1,3 @@
+import a from "./a.module.css";
+
+console.log(a);
@@ -0,0 +1,33 @@
+(self['webpackChunkwebpack'] = self['webpackChunkwebpack'] || []).push([["main"], {
+"./index.js": function (module, exports, __webpack_require__) {
+"use strict";
+Object.defineProperty(exports, "__esModule", {
+    value: true
+});
+var _acssmodules = __webpack_require__.ir(__webpack_require__("./a.css?modules"));
+__webpack_require__("./a.css");
+__webpack_require__("./b.module.css?modules=false");
+var _bmodulecss = __webpack_require__.ir(__webpack_require__("./b.module.css"));
+console.log(_acssmodules.default, _bmodulecss.default);
+},
+"./b.module.css": function (module, exports, __webpack_require__) {
+module.exports = {
+  "b": "b__c1560168",
+};
+},
+"./b.module.css?modules=false": function (module, exports, __webpack_require__) {
+},
+"./a.css": function (module, exports, __webpack_require__) {
+},
+"./a.css?modules": function (module, exports, __webpack_require__) {
+module.exports = {
+  "a": "a___175a9f0",
+};
+},
+
+},function(__webpack_require__) {
+var __webpack_exec__ = function(moduleId) { return __webpack_require__(__webpack_require__.s = moduleId) }
+var __webpack_exports__ = (__webpack_exec__("./index.js"));
+
+}
+]);
\ No newline at end of file
@@ -0,0 +1,6 @@
+import a from "./a.css?modules";
+import "./a.css";
+import "./b.module.css?modules=false";
+import b from "./b.module.css";
+
+console.log(a, b);
@@ -0,0 +1,49 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use rspack_plugin_css::normalize_url;
+
+/// Counts the allocations of the current thread, so the other tests running don't add to them.
+struct CountingAllocator;
+
+thread_local! {
+  static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+    System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations(f: impl FnOnce()) -> usize {
+  let before = ALLOCATIONS.with(Cell::get);
+  f();
+  ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn plain_urls_are_normalized_without_allocating() {
+  let urls = (0..300)
+    .map(|i| format!("./images/icon-{i}.png"))
+    .collect::<Vec<_>>();
+  // Lazily built regexes and the like aren't counted.
+  normalize_url("a\\ %20.png");
+  assert_eq!(
+    allocations(|| {
+      for url in &urls {
+        assert_eq!(normalize_url(url), url.as_str());
+      }
+    }),
+    0
+  );
+  assert_ne!(allocations(|| drop(normalize_url("a%20.png"))), 0);
+}
@@ -186,6 +186,14 @@ pub struct Builtins {
 pub struct Css {
   #[serde(default)]
   pub modules: ModulesConfig,
+  #[serde(default)]
+  pub relative_urls: bool,
+  #[serde(default)]
+  pub lenient_urls: bool,
+  #[serde(default)]
+  pub keep_url_percent_escapes: bool,
+  #[serde(default)]
+  pub debug_counters: bool,
 }
 
 #[derive(Debug, JsonSchema, Deserialize)]
@@ -193,7 +201,14 @@ pub struct Css {
 pub struct ModulesConfig {
   pub locals_convention: String,
   pub local_ident_name: String,
+  pub hash_strategy: String,
+  pub hash_prefix: Option<String>,
+  pub hash_digest_length: Option<usize>,
+  pub hash_salt: Option<String>,
   pub exports_only: bool,
+  pub es_module: bool,
+  pub exports_type: Option<String>,
+  pub ident_manifest: Option<String>,
 }
 
 impl Default for ModulesConfig {
@@ -201,7 +216,14 @@ impl Default for ModulesConfig {
     Self {
       locals_convention: "asIs".to_string(),
       local_ident_name: "[path][name][ext]__[local]".to_string(),
+      hash_strategy: "resource-path".to_string(),
+      hash_prefix: None,
+      hash_digest_length: None,
+      hash_salt: None,
       exports_only: false,
+      es_module: false,
+      exports_type: None,
+      ident_manifest: None,
     }
   }
 }
@@ -526,11 +548,64 @@ impl TestConfig {
             &self.builtins.css.modules.locals_convention,
           )
           .expect("Invalid css.modules.locals_convention"),
-          local_ident_name: rspack_plugin_css::plugin::LocalIdentName::from(
-            self.builtins.css.modules.local_ident_name,
-          ),
+          local_ident_name: rspack_plugin_css::plugin::LocalIdentName::from_str(
+            &self.builtins.css.modules.local_ident_name,
+          )
+          .expect("Invalid css.modules.local_ident_name"),
+          local_ident_regexp: None,
+          hash_strategy: rspack_plugin_css::plugin::LocalIdentHashStrategy::from_str(
+            &self.builtins.css.modules.hash_strategy,
+          )
+          .expect("Invalid css.modules.hash_strategy"),
+          hash_prefix: self.builtins.css.modules.hash_prefix,
+          prefix: None,
+          local_ident_max_length: None,
+          hash_function: None,
+          hash_digest: None,
+          hash_digest_length: self.builtins.css.modules.hash_digest_length,
+          hash_salt: self
+            .builtins
+            .css
+            .modules
+            .hash_salt
+            .map(|salt| Some(salt).into()),
           exports_only: self.builtins.css.modules.exports_only,
+          exports_type: match &self.builtins.css.modules.exports_type {
+            Some(exports_type) => rspack_plugin_css::plugin::ExportsType::from_str(exports_type)
+              .expect("Invalid css.modules.exports_type"),
+            None if self.builtins.css.modules.es_module => {
+              rspack_plugin_css::plugin::ExportsType::Default
+            }
+            None => rspack_plugin_css::plugin::ExportsType::CommonJs,
+          },
+          named_export: false,
+          export_globals: false,
+          exports_by_convention: false,
+          composes_separator: " ".to_string(),
+          export_class_names: rspack_plugin_css::plugin::ExportClassNames::String,
+          lazy_composes: false,
+          sort_exports: false,
+          pathinfo: false,
+          single_quote: false,
+          freeze_exports: false,
+          resilient: false,
+          line_ending: rspack_plugin_css::plugin::LineEnding::Lf,
+          trailing_newline: true,
+          detect_ident_collisions: false,
+          pure: false,
+          ident_manifest: self.builtins.css.modules.ident_manifest,
+          export_content_hash: false,
+          hash_package_name: false,
+          ident_start: rspack_plugin_css::plugin::IdentStart::Underscore,
+          invalid_escape: rspack_plugin_css::plugin::InvalidEscape::Replace,
+          local_scope: rspack_plugin_css::plugin::LocalScope::Class,
+          local_ident_fn: None,
         },
+        relative_urls: self.builtins.css.relative_urls,
+        lenient_urls: self.builtins.css.lenient_urls,
+        keep_url_percent_escapes: self.builtins.css.keep_url_percent_escapes,
+        debug_counters: self.builtins.css.debug_counters,
+        record_hashes: false,
       })
       .boxed(),
     );
@@ -45,6 +45,10 @@ export type EmotionConfig =
 
 export type CssPluginConfig = {
 	modules?: Partial<RawCssModulesConfig>;
+	relativeUrls?: boolean;
+	lenientUrls?: boolean;
+	keepUrlPercentEscapes?: boolean;
+	debugCounters?: boolean;
 };
 
 export type MinificationConfig = {
@@ -375,7 +379,11 @@ export function resolveBuiltinsOptions(
 				localIdentName: production ? "[hash]" : "[path][name][ext]__[local]",
 				exportsOnly: false,
 				...builtins.css?.modules
-			}
+			},
+			relativeUrls: builtins.css?.relativeUrls,
+			lenientUrls: builtins.css?.lenientUrls,
+			keepUrlPercentEscapes: builtins.css?.keepUrlPercentEscapes,
+			debugCounters: builtins.css?.debugCounters
 		},
 		postcss: { pxtorem: undefined, ...builtins.postcss },
 		treeShaking: resolveTreeShaking(builtins.treeShaking, production),
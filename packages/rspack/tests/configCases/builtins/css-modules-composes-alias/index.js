@@ -0,0 +1,8 @@
+it("css modules composes through aliases", () => {
+	const style = require("./index.css");
+	const shared = require("@styles/shared.css");
+	expect(shared.shared).toBeTruthy();
+	expect(style.aliased).toBe(`index-css__aliased ${shared.shared}`);
+	// Composed with the request rewritten by `beforeResolve`.
+	expect(style.rewritten).toBe(`index-css__rewritten ${shared.shared}`);
+});
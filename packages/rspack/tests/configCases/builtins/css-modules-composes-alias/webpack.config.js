@@ -0,0 +1,37 @@
+const path = require("path");
+
+class RewriteLegacyStylesPlugin {
+	apply(compiler) {
+		compiler.hooks.compilation.tap(
+			"RewriteLegacyStylesPlugin",
+			(compilation, { normalModuleFactory }) => {
+				normalModuleFactory.hooks.beforeResolve.tap(
+					"RewriteLegacyStylesPlugin",
+					resolveData => {
+						resolveData.request = resolveData.request.replace(
+							/^@legacy-styles\//,
+							"@styles/"
+						);
+					}
+				);
+			}
+		);
+	}
+}
+
+module.exports = {
+	resolve: {
+		alias: {
+			"@styles": path.resolve(__dirname, "styles")
+		}
+	},
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	},
+	plugins: [new RewriteLegacyStylesPlugin()]
+};
@@ -0,0 +1,5 @@
+it("css modules composes chain", () => {
+	const style = require("./index.css");
+	expect(style.a).toBe("index-css__a b-css__b c-css__c");
+	expect(style.d).toBe("index-css__d");
+});
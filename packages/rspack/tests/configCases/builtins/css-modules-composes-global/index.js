@@ -0,0 +1,5 @@
+it("css modules composes from global", () => {
+	const style = require("./index.css");
+	expect(style.foo).toBe("index-css__foo shared");
+	expect(style.bar).toBe("index-css__bar index-css__foo one two");
+});
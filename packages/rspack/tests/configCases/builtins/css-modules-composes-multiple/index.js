@@ -0,0 +1,6 @@
+it("css modules composes from multiple files", () => {
+	const style = require("./index.css");
+	expect(style.simple).toBe(
+		"index-css__simple a-css__foo b-css__foo b-css__bar"
+	);
+});
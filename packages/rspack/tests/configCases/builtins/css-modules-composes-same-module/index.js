@@ -0,0 +1,7 @@
+it("css modules composes from the same module imported twice", () => {
+	const style = require("./index.css");
+	expect(style).toEqual({
+		foo: "index-css__foo shared-css__a",
+		bar: "index-css__bar shared-css__b"
+	});
+});
@@ -0,0 +1,10 @@
+module.exports = {
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	}
+};
@@ -0,0 +1,12 @@
+import style, { simple } from "./index.css";
+import * as namespace from "./index.css";
+
+it("css modules esModule", () => {
+	expect(simple).toBe("index-css__simple");
+	expect(style.simple).toBe(simple);
+	expect(style["foo-bar"]).toBe(
+		"index-css__foo-bar imported-simple-css__imported-simple"
+	);
+	expect(namespace.simple).toBe(simple);
+	expect(namespace["foo-bar"]).toBeUndefined();
+});
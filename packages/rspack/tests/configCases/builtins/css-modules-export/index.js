@@ -0,0 +1,9 @@
+it("css modules :export", () => {
+	const style = require("./index.css");
+	expect(style).toEqual({
+		primaryColor: "#fff",
+		spacing: "4px 8px",
+		btn: "index-css__btn",
+		"btn-primary": "index-css__btn-primary index-css__btn"
+	});
+});
@@ -0,0 +1,11 @@
+it("css modules freeze exports", () => {
+	const style = require("./index.css");
+	expect(Object.isFrozen(style)).toBe(true);
+	expect(style.foo).toBe("index-css__foo");
+	expect(style.bar).toBe("index-css__bar index-css__foo");
+	expect(() => {
+		"use strict";
+		style.foo = "other";
+	}).toThrow(TypeError);
+	expect(style.foo).toBe("index-css__foo");
+});
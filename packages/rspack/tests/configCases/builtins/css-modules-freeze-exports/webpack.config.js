@@ -0,0 +1,17 @@
+module.exports = {
+	builtins: {
+		css: {
+			modules: {
+				freezeExports: true
+			}
+		}
+	},
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	}
+};
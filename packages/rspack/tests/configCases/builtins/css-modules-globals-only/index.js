@@ -0,0 +1,7 @@
+import style from "./index.css";
+import * as namespace from "./index.css";
+
+it("css modules without exports", () => {
+	expect(style).toEqual({});
+	expect(Object.keys(namespace)).toEqual(["default"]);
+});
@@ -0,0 +1,17 @@
+module.exports = {
+	builtins: {
+		css: {
+			modules: {
+				esModule: true
+			}
+		}
+	},
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	}
+};
@@ -0,0 +1,14 @@
+const fs = require("fs");
+
+it("css modules exports are safe to inline in html", () => {
+	const style = require("./index.css");
+	// Built by concatenation so the test code itself doesn't contain the sequences.
+	const lt = "<";
+	expect(style.danger).toBe(`"${lt}/script>${lt}!--"`);
+	expect(Object.keys(style)).toContain(`${lt}/style>`);
+
+	const out = fs.readFileSync(__filename, "utf-8");
+	expect(out).not.toContain(`${lt}/script`);
+	expect(out).not.toContain(`${lt}/style`);
+	expect(out).not.toContain(`${lt}!--`);
+});
@@ -0,0 +1,2 @@
+globalThis.cssModulesLazyComposes.push("composed");
+module.exports = { composed: "composed-class" };
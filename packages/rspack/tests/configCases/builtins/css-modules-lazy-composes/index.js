@@ -0,0 +1,15 @@
+it("css modules lazy composes", () => {
+	globalThis.cssModulesLazyComposes = [];
+	const style = require("./index.css");
+	globalThis.cssModulesLazyComposes.push("index");
+	expect(style.foo).toBe("index-css__foo");
+	expect(globalThis.cssModulesLazyComposes).toEqual(["index"]);
+	expect(style.bar).toBe("index-css__bar composed-class");
+	expect(globalThis.cssModulesLazyComposes).toEqual(["index", "composed"]);
+	// Enumerable like the locals without composes.
+	expect(Object.keys(style).sort()).toEqual(["bar", "foo"]);
+	expect(JSON.parse(JSON.stringify(style))).toEqual({
+		foo: "index-css__foo",
+		bar: "index-css__bar composed-class"
+	});
+});
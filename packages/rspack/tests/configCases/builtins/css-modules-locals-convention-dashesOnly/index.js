@@ -0,0 +1,9 @@
+it("css modules localsConvention with dashesOnly", () => {
+	const style = require("./index.css");
+	expect(style).toEqual({
+		"btn-info-is-disabled": "index-css__btn-info_is-disabled",
+		"btn-info-is-disabled-1": "index-css__btn--info_is-disabled_1",
+		"foo-bar": "index-css__foo_bar",
+		simple: "index-css__simple"
+	});
+});
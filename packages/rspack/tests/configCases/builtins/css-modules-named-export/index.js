@@ -0,0 +1,10 @@
+import * as styles from "./index.css";
+
+it("css modules namedExport", () => {
+	expect(styles.simple).toBe("index-css__simple");
+	expect(styles.fooBar).toBe(
+		"index-css__foo-bar imported-simple-css__imported-simple"
+	);
+	expect(styles["foo-bar"]).toBeUndefined();
+	expect(styles.default).toBeUndefined();
+});
@@ -0,0 +1,18 @@
+module.exports = {
+	builtins: {
+		css: {
+			modules: {
+				localsConvention: "camelCaseOnly",
+				namedExport: true
+			}
+		}
+	},
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	}
+};
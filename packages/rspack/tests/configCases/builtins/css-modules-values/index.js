@@ -0,0 +1,11 @@
+it("css modules @value", () => {
+	const style = require("./index.css");
+	expect(style).toEqual({
+		accent: "blue",
+		border: "4px solid red",
+		colors: '"./colors.css"',
+		foo: "index-css__foo",
+		primary: "#fff",
+		size: "4px"
+	});
+});
@@ -0,0 +1,12 @@
+require("./index.css");
+
+it("should error when composing from a module other than a css module", () => {
+	const messages = __STATS__.errors.map(error => error.message);
+	expect(
+		messages.some(message =>
+			message.includes(
+				'`composes` from "./theme.js" resolves to a javascript/auto module'
+			)
+		)
+	).toBe(true);
+});
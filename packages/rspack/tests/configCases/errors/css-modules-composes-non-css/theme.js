@@ -0,0 +1 @@
+module.exports = { primary: "primary" };
@@ -0,0 +1,13 @@
+require("./a.css");
+require("./b.css");
+
+it("should error when locals are renamed to the same ident", () => {
+	const messages = __STATS__.errors.map(error => error.message);
+	expect(
+		messages.some(message =>
+			message.includes(
+				"use a longer `hashDigestLength` or add `[local]` to `localIdentName` to tell them apart."
+			)
+		)
+	).toBe(true);
+});
@@ -0,0 +1,20 @@
+module.exports = {
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	},
+	builtins: {
+		css: {
+			modules: {
+				// 17 locals and 16 single character hashes, two of them collide.
+				localIdentName: "[hash]",
+				hashDigestLength: 1,
+				detectIdentCollisions: true
+			}
+		}
+	}
+};
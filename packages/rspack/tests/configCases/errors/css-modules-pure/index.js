@@ -0,0 +1,14 @@
+require("./index.css");
+
+it("should error for the selectors of a pure css module without a local", () => {
+	const messages = __STATS__.errors.map(error => error.message);
+	expect(
+		messages.some(message => message.includes("selector `body` is not pure"))
+	).toBe(true);
+	expect(
+		messages.some(message =>
+			message.includes("selector `:global(.theme-dark)` is not pure")
+		)
+	).toBe(true);
+	expect(messages.some(message => message.includes(".card"))).toBe(false);
+});
@@ -0,0 +1,27 @@
+const fs = require("fs");
+
+require("./index.css");
+
+it("verify css modules exports source map", async () => {
+	const sourceMap = require("source-map");
+	const source = fs.readFileSync(__filename + ".map", "utf-8");
+	const map = await new sourceMap.SourceMapConsumer(source);
+	const out = fs.readFileSync(__filename, "utf-8");
+	const positionOf = key => {
+		const lines = out.slice(0, out.indexOf(`"${key}": "`)).split("\n");
+		return map.originalPositionFor({
+			line: lines.length,
+			column: lines[lines.length - 1].length
+		});
+	};
+	expect(positionOf("foo")).toMatchObject({
+		source: "./index.css",
+		line: 1,
+		column: 0
+	});
+	expect(positionOf("bar")).toMatchObject({
+		source: "./index.css",
+		line: 5,
+		column: 0
+	});
+});
@@ -0,0 +1,13 @@
+module.exports = {
+	devtool: "source-map",
+	externals: ["source-map"],
+	externalsType: "commonjs",
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	}
+};
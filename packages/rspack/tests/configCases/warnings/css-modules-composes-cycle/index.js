@@ -0,0 +1,11 @@
+import a from "./a.css";
+import b from "./b.css";
+
+it("should warn about composes cycles and still compose the classes", () => {
+	const messages = __STATS__.warnings.map(warning => warning.message);
+	expect(
+		messages.some(message => message.includes("`composes` forms a cycle"))
+	).toBe(true);
+	expect(a.a.split(" ")).toEqual([expect.any(String), b.b.split(" ")[0], a.c]);
+	expect(b.b.split(" ")).toEqual([expect.any(String), a.c]);
+});
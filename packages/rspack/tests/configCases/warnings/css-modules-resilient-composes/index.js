@@ -0,0 +1,12 @@
+it("should keep the exports of a css module composing from a missing file", () => {
+	const styles = require("./index.css");
+	expect(styles.button).toBeDefined();
+	const messages = __STATS__.warnings.map(warning => warning.message);
+	expect(
+		messages.some(message =>
+			message.includes(
+				'`composes` from "./theme.css" can\'t be resolved, the classes composed from it are left out until it can.'
+			)
+		)
+	).toBe(true);
+});
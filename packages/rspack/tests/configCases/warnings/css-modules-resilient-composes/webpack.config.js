@@ -0,0 +1,17 @@
+module.exports = {
+	module: {
+		rules: [
+			{
+				test: /\.css$/,
+				type: "css/module"
+			}
+		]
+	},
+	builtins: {
+		css: {
+			modules: {
+				resilient: true
+			}
+		}
+	}
+};